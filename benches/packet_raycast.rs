@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use space::raycast::{
+    packet::{compute_packet_intersections, RayPacket4, RayPacket4x2},
+    primitives::{Backfaces, IntersectionAlgorithm},
+    ray::Ray3d,
+    triangles::MeshTriangles,
+    update_raycast::compute_triangles_intersection,
+};
+
+/// A grid of rays fanning out from the origin towards the sphere of [packet_raycast].
+fn rays(count: usize) -> Vec<Ray3d> {
+    let side = (count as f32).sqrt() as usize;
+    (0..count)
+        .map(|i| {
+            let x = (i % side) as f32 / side as f32 - 0.5;
+            let y = (i / side) as f32 / side as f32 - 0.5;
+            Ray3d::new(Vec3::ZERO, Vec3::new(x * 0.5, y * 0.5, -1.0))
+        })
+        .collect()
+}
+
+/// Compares casting rays one at a time against casting them in packets of four, alone or in pairs.
+fn packet_raycast(c: &mut Criterion) {
+    let mesh = Mesh::from(shape::Icosphere {
+        radius: 10.0,
+        subdivisions: 3,
+    });
+    let triangles = MeshTriangles::from_mesh(&mesh).unwrap();
+    let mesh_to_world = Mat4::from_translation(Vec3::new(0.0, 0.0, -50.0));
+
+    let mut group = c.benchmark_group("packet_raycast");
+    for count in [16, 256] {
+        let rays = rays(count);
+        group.bench_with_input(BenchmarkId::new("scalar", count), &rays, |b, rays| {
+            b.iter(|| {
+                rays.iter()
+                    .map(|ray| {
+                        compute_triangles_intersection(
+                            &mesh_to_world,
+                            &triangles,
+                            ray,
+                            Backfaces::Cull,
+                            IntersectionAlgorithm::MollerTrumbore,
+                            f32::MAX,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("packet4", count), &rays, |b, rays| {
+            b.iter(|| {
+                compute_packet_intersections::<RayPacket4>(
+                    &mesh_to_world,
+                    &triangles,
+                    black_box(rays),
+                    Backfaces::Cull,
+                    f32::MAX,
+                )
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("packet4x2", count), &rays, |b, rays| {
+            b.iter(|| {
+                compute_packet_intersections::<RayPacket4x2>(
+                    &mesh_to_world,
+                    &triangles,
+                    black_box(rays),
+                    Backfaces::Cull,
+                    f32::MAX,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, packet_raycast);
+criterion_main!(benches);
@@ -14,7 +14,7 @@ use space::{
     lock_on::LockOnPlugin,
     origin::{OriginRebasingPlugin, SimulationBundle},
     projectile::ProjectilePlugin,
-    raycast::{event::HoverEvent, RayCastMesh, RayCastSource, RaycastPlugin},
+    raycast::{event::HoverEvent, RayCastHits, RayCastMesh, RayCastSource, RaycastPlugin},
     tag::{MyRaycastSet, PlayerModelTag, PlayerTag},
     util::setup_crosshair,
 };
@@ -174,14 +174,15 @@ fn spawn_light(mut commands: Commands) {
 
 // #[allow(clippy::type_complexity)] <T: Asset>
 fn highlight_marker(
-    query: Query<&mut RayCastSource<MyRaycastSet>>,
+    query: Query<&RayCastHits<MyRaycastSet>>,
     raycast_meshes: Query<&Name, With<RayCastMesh<MyRaycastSet>>>,
 ) {
-    let source = query.single();
-    for (entity, _) in source.intersections.iter() {
-        if let Ok(name) = raycast_meshes.get(*entity) {
-            // visibility.is_visible = false;
-            println!("{:?}", name);
+    for hits in query.iter() {
+        for (entity, _) in hits.iter() {
+            if let Ok(name) = raycast_meshes.get(*entity) {
+                // visibility.is_visible = false;
+                println!("{:?}", name);
+            }
         }
     }
 }
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+use space::projectile::{
+    countermeasure::{Countermeasure, CountermeasureDefinition, CountermeasureRng},
+    seeker::{SeekerKind, SeekerParams},
+};
+
+const ENGAGEMENTS: u32 = 1000;
+const FLARES: u32 = 3;
+const SEED: u64 = 42;
+
+/// Rolls seekers against bursts of flares, without rendering, to show how often decoys break the
+/// lock. The rng is seeded, so every run prints the same numbers.
+fn main() {
+    let infrared = SeekerParams {
+        kind: SeekerKind::Infrared,
+        half_angle: 0.5,
+        range: 1000.0,
+        counter_resistance: 0.0,
+    };
+    let hardened = SeekerParams {
+        counter_resistance: 0.5,
+        ..infrared
+    };
+    let radar = SeekerParams {
+        kind: SeekerKind::Radar,
+        ..infrared
+    };
+
+    let flare: CountermeasureDefinition = ron::from_str(include_str!(
+        "../assets/countermeasures/flare.countermeasure.ron"
+    ))
+    .unwrap();
+
+    for (name, seeker) in [
+        ("infrared", infrared),
+        ("hardened infrared", hardened),
+        ("radar", radar),
+    ] {
+        let first = broken_locks(&seeker, &flare, SEED);
+        let replay = broken_locks(&seeker, &flare, SEED);
+        assert_eq!(first, replay, "the same seed must give the same outcome");
+        println!(
+            "{}: {} of {} locks broken by {} flares",
+            name, first, ENGAGEMENTS, FLARES
+        );
+    }
+}
+
+/// Number of engagements in which the seeker ends up tracking a flare instead of the target.
+fn broken_locks(seeker: &SeekerParams, flare: &CountermeasureDefinition, seed: u64) -> u32 {
+    let mut rng = CountermeasureRng::from_seed(seed);
+    let target = Entity::from_raw(0);
+    let target_position = Vec3::new(0.0, 0.0, -500.0);
+
+    let mut broken = 0;
+    for _ in 0..ENGAGEMENTS {
+        let decoys: Vec<_> = (1..=FLARES)
+            .map(|index| {
+                let flare = Countermeasure::new(flare, Vec3::ZERO);
+                let position = target_position + Vec3::new(index as f32 * 5.0, 0.0, 0.0);
+                flare.decoy(Entity::from_raw(index), position)
+            })
+            .collect();
+        let mut evaluated = Vec::new();
+        let tracked = seeker.reacquire(
+            Vec3::ZERO,
+            -Vec3::Z,
+            Some((target, target_position)),
+            &decoys,
+            &mut evaluated,
+            &mut rng.0,
+        );
+        if tracked != Some(target) {
+            broken += 1;
+        }
+    }
+    broken
+}
@@ -6,10 +6,11 @@ use bevy::{
 
 use space::{
     camera::tag::*,
+    damage::{health::Health, shield::Shield, DamagePlugin},
     fps::FpsPlugin,
     lock_on::LockOnPlugin,
     origin::{OriginRebasingPlugin, SimulationBundle},
-    projectile::{ProjectilePlugin, Target},
+    projectile::{event::ProjectileHitEvent, ProjectilePlugin, Target},
     raycast::{RayCastMesh, RayCastSource, RaycastPlugin},
     tag::{MyRaycastSet, PlayerModelTag, PlayerTag},
     util::setup_crosshair,
@@ -20,6 +21,9 @@ use space::{
 };
 use space::{scale::*, util::setup_cursor};
 
+const TARGET_HEALTH: f32 = 500.0;
+const TARGET_SHIELD: f32 = 100.0;
+
 #[derive(Component)]
 pub struct Player;
 
@@ -43,12 +47,14 @@ fn main() {
         .add_plugin(FpsPlugin)
         .add_plugin(LockOnPlugin)
         .add_plugin(ProjectilePlugin)
+        .add_plugin(DamagePlugin)
         .add_startup_system(setup)
         .add_startup_system(setup_cursor)
         .add_startup_system(spawn_target)
         .add_startup_system(spawn_light)
         .add_startup_system(setup_crosshair)
         .add_system(move_target)
+        .add_system(log_hits)
         // .add_system(handle_lock_on)
         // .add_system(highlight_marker)
         .run();
@@ -130,6 +136,8 @@ fn spawn_target(
                 ..Default::default()
             })
             .insert(Target::default())
+            .insert(Health::new(TARGET_HEALTH))
+            .insert(Shield::new(TARGET_SHIELD, 20.0, 3.0))
             .insert(Name::new(name))
             .insert(RayCastMesh::<MyRaycastSet>::default());
     };
@@ -171,3 +179,16 @@ fn move_target(time: Res<Time>, mut query: Query<(&mut Transform, &mut Target)>)
         transform.translation += target.velocity * time.delta_seconds();
     }
 }
+
+fn log_hits(mut hit_events: EventReader<ProjectileHitEvent>, names: Query<&Name>) {
+    for event in hit_events.iter() {
+        if let Ok(name) = names.get(event.target) {
+            println!(
+                "{:?} hit {} at {}",
+                event.kind,
+                name.as_str(),
+                event.intersection.position
+            );
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use space::raycast::{
+    packet::{compute_packet_intersections, RayPacket, RayPacket4, RayPacket4x2},
+    primitives::{Backfaces, Intersection, IntersectionAlgorithm},
+    ray::Ray3d,
+    triangles::MeshTriangles,
+    update_raycast::compute_triangles_intersection,
+};
+
+const RAY_COUNT: usize = 4096;
+
+/// Compares casting a batch of rays one at a time against casting them in SIMD packets of four,
+/// alone or in pairs. See `benches/packet_raycast.rs` for steadier numbers.
+fn main() {
+    let mesh = Mesh::from(shape::Icosphere {
+        radius: 10.0,
+        subdivisions: 5,
+    });
+    let triangles = MeshTriangles::from_mesh(&mesh).unwrap();
+    let mesh_to_world = Mat4::from_translation(Vec3::new(0.0, 0.0, -50.0));
+
+    // A grid of rays fanning out from the origin towards the sphere
+    let side = (RAY_COUNT as f32).sqrt() as usize;
+    let rays: Vec<Ray3d> = (0..RAY_COUNT)
+        .map(|i| {
+            let x = (i % side) as f32 / side as f32 - 0.5;
+            let y = (i / side) as f32 / side as f32 - 0.5;
+            Ray3d::new(Vec3::ZERO, Vec3::new(x * 0.5, y * 0.5, -1.0))
+        })
+        .collect();
+
+    println!(
+        "Casting {} rays against {} triangles",
+        rays.len(),
+        triangles.len()
+    );
+
+    let start = Instant::now();
+    let scalar: Vec<_> = rays
+        .iter()
+        .map(|ray| {
+            compute_triangles_intersection(
+                &mesh_to_world,
+                &triangles,
+                ray,
+                Backfaces::Cull,
+                IntersectionAlgorithm::MollerTrumbore,
+                f32::MAX,
+            )
+        })
+        .collect();
+    let scalar_time = start.elapsed();
+
+    let hits = scalar.iter().filter(|hit| hit.is_some()).count();
+    println!("Hits: {}", hits);
+    println!("Scalar: {:?}", scalar_time);
+    compare_packets::<RayPacket4>(
+        "Packet4",
+        &mesh_to_world,
+        &triangles,
+        &rays,
+        &scalar,
+        scalar_time,
+    );
+    compare_packets::<RayPacket4x2>(
+        "Packet4x2",
+        &mesh_to_world,
+        &triangles,
+        &rays,
+        &scalar,
+        scalar_time,
+    );
+}
+
+fn compare_packets<P: RayPacket>(
+    name: &str,
+    mesh_to_world: &Mat4,
+    triangles: &MeshTriangles,
+    rays: &[Ray3d],
+    scalar: &[Option<Intersection>],
+    scalar_time: Duration,
+) {
+    let start = Instant::now();
+    let packet = compute_packet_intersections::<P>(
+        mesh_to_world,
+        triangles,
+        rays,
+        Backfaces::Cull,
+        f32::MAX,
+    );
+    let packet_time = start.elapsed();
+
+    let mismatches = scalar
+        .iter()
+        .zip(&packet)
+        .filter(|(scalar, packet)| match (scalar, packet) {
+            (Some(scalar), Some(packet)) => (scalar.distance() - packet.distance()).abs() > 1e-3,
+            (None, None) => false,
+            _ => true,
+        })
+        .count();
+
+    println!(
+        "{}: {:?} ({:.2}x), mismatches: {}",
+        name,
+        packet_time,
+        scalar_time.as_secs_f64() / packet_time.as_secs_f64(),
+        mismatches
+    );
+}
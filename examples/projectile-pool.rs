@@ -0,0 +1,43 @@
+use bevy::{ecs::system::CommandQueue, prelude::*};
+
+use space::projectile::pool::{PooledProjectile, ProjectilePool};
+
+const TICKS: usize = 3000;
+/// Ticks a projectile flies before it is spent, e.g. 50 rounds per second lasting 2 seconds.
+const FLIGHT_TICKS: usize = 100;
+
+/// Fires a projectile every tick through a [ProjectilePool], without rendering, and prints the
+/// number of entities in the world, which stops growing once the first projectiles are spent.
+fn main() {
+    let mut world = World::new();
+    let mut pool = ProjectilePool::default();
+    let weapon = Handle::default();
+    let mut in_flight = Vec::new();
+    let mut peak = 0;
+
+    for tick in 0..TICKS {
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        if in_flight.len() == FLIGHT_TICKS {
+            let spent: Entity = in_flight.remove(0);
+            let pooled = world.get::<PooledProjectile>(spent).cloned();
+            pool.release(&mut commands, spent, pooled.as_ref());
+        }
+        let projectile = pool
+            .spawn(&mut commands, &weapon, PbrBundle::default())
+            .id();
+        queue.apply(&mut world);
+        in_flight.push(projectile);
+
+        let entities = world.entities().len();
+        if tick > FLIGHT_TICKS {
+            assert!(entities <= peak, "the pool should reuse spent projectiles");
+        }
+        peak = peak.max(entities);
+    }
+    println!(
+        "{} projectiles fired, {} entities in the world",
+        TICKS,
+        world.entities().len()
+    );
+}
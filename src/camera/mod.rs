@@ -102,9 +102,9 @@ fn handle_lock_on_events(
     // mut raycast_meshes: Query<(&Name, &mut Transform), With<RayCastMesh<MyRaycastSet>>>,
 ) {
     for event in lock_on_events_reader.iter() {
-        if let LockOnEvent::Attached(entity) = event {
-            lock_on_state.target = Some(*entity);
-        } else if let LockOnEvent::Released = event {
+        if let LockOnEvent::Attached { target, .. } = event {
+            lock_on_state.target = Some(*target);
+        } else if let LockOnEvent::Released { .. } = event {
             println!("Release Lock-On");
             lock_on_state.target = None;
         }
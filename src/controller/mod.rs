@@ -275,9 +275,9 @@ fn handle_lock_on_events(
     mut lock_on_state: ResMut<LockOnState>,
 ) {
     for event in lock_on_events.iter() {
-        if let LockOnEvent::Attached(entity) = event {
-            lock_on_state.target = Some(*entity);
-        } else if let LockOnEvent::Released = event {
+        if let LockOnEvent::Attached { target, .. } = event {
+            lock_on_state.target = Some(*target);
+        } else if let LockOnEvent::Released { .. } = event {
             lock_on_state.target = None;
         }
     }
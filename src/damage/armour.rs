@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArmourFacing {
+    Front,
+    Rear,
+    Sides,
+}
+
+impl ArmourFacing {
+    /// Returns the facing struck by a hit with the given surface normal, in the local space of the
+    /// target. The front of a ship faces -Z, like cameras and [Transform::forward].
+    pub fn from_local_normal(normal: Vec3) -> Self {
+        let abs = normal.abs();
+        if abs.z >= abs.x && abs.z >= abs.y {
+            if normal.z < 0.0 {
+                ArmourFacing::Front
+            } else {
+                ArmourFacing::Rear
+            }
+        } else {
+            ArmourFacing::Sides
+        }
+    }
+}
+
+/// Fraction of the damage stopped by the armour on each facing, from 0 for none to 1 for all.
+/// Applies to damage that got through the [Shield](super::shield::Shield).
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Armour {
+    pub front: f32,
+    pub rear: f32,
+    pub sides: f32,
+}
+
+impl Armour {
+    pub fn get(&self, facing: ArmourFacing) -> f32 {
+        match facing {
+            ArmourFacing::Front => self.front,
+            ArmourFacing::Rear => self.rear,
+            ArmourFacing::Sides => self.sides,
+        }
+    }
+
+    /// Returns the damage left after the armour of the facing struck by a hit with the given
+    /// world space normal.
+    pub fn apply(&self, amount: f32, normal: Vec3, target_transform: &GlobalTransform) -> f32 {
+        let local_normal = target_transform.rotation.inverse() * normal;
+        let facing = ArmourFacing::from_local_normal(local_normal);
+        amount * (1.0 - self.get(facing).clamp(0.0, 1.0))
+    }
+}
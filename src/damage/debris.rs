@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+/// Spawns pieces of debris flying away from the entity when it is destroyed.
+#[derive(Component, Clone)]
+pub struct Debris {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+    pub count: usize,
+    pub speed: f32,
+    pub scale: f32,
+    /// Seconds before the pieces are despawned.
+    pub lifetime: f32,
+}
+
+#[derive(Component)]
+pub struct DebrisPiece {
+    pub velocity: Vec3,
+    pub timer: Timer,
+}
+
+/// Direction of the `index`-th of `count` pieces, spread evenly over a sphere.
+pub fn debris_direction(index: usize, count: usize) -> Vec3 {
+    // Fibonacci sphere
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+    let y = 1.0 - 2.0 * (index as f32 + 0.5) / count.max(1) as f32;
+    let radius = (1.0 - y * y).sqrt();
+    let theta = golden_angle * index as f32;
+    Vec3::new(theta.cos() * radius, y, theta.sin() * radius)
+}
+
+pub fn spawn_debris(commands: &mut Commands, debris: &Debris, transform: &GlobalTransform) {
+    for index in 0..debris.count {
+        let direction = debris_direction(index, debris.count);
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: debris.mesh.clone(),
+                material: debris.material.clone(),
+                transform: Transform::from_translation(transform.translation)
+                    .with_scale(Vec3::splat(debris.scale)),
+                ..Default::default()
+            })
+            .insert(Name::new("Debris"))
+            .insert(DebrisPiece {
+                velocity: direction * debris.speed,
+                timer: Timer::from_seconds(debris.lifetime, false),
+            });
+    }
+}
+
+pub fn update_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &mut DebrisPiece, Entity)>,
+) {
+    for (mut transform, mut piece, entity) in query.iter_mut() {
+        if piece.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            transform.translation += piece.velocity * time.delta_seconds();
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+
+use super::health::Damage;
+
+/// Damage dealt to an entity with [Health](super::health::Health), before resistances.
+#[derive(Debug, Clone, Copy)]
+pub struct DamageEvent {
+    /// Entity struck, which is either the one with [Health](super::health::Health) or one of its
+    /// descendants, e.g. a mesh of a ship.
+    pub target: Entity,
+    /// Entity that dealt the damage, e.g. a projectile. Pooled projectile entities are reused by
+    /// later shots once spent, told apart by `source_generation`.
+    pub source: Option<Entity>,
+    /// [PooledProjectile::generation](crate::projectile::pool::PooledProjectile::generation) of
+    /// the source, or 0 if it is not a pooled projectile.
+    pub source_generation: u32,
+    pub damage: Damage,
+    pub position: Vec3,
+    /// World space surface normal at the hit, used to find the [Armour](super::armour::Armour)
+    /// facing that was struck. Damage without a normal ignores armour.
+    pub normal: Option<Vec3>,
+}
+
+/// Sent once when the health of an entity reaches zero, before it is despawned.
+#[derive(Debug, Clone, Copy)]
+pub struct DestroyedEvent {
+    pub entity: Entity,
+    pub source: Option<Entity>,
+    /// See [DamageEvent::source_generation].
+    pub source_generation: u32,
+    pub position: Vec3,
+}
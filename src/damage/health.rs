@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum DamageType {
+    Kinetic,
+    Explosive,
+    Energy,
+}
+
+impl Default for DamageType {
+    fn default() -> Self {
+        DamageType::Kinetic
+    }
+}
+
+/// An amount of damage of a single type, before resistances.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+pub struct Damage {
+    pub amount: f32,
+    pub damage_type: DamageType,
+}
+
+impl Damage {
+    pub fn new(amount: f32, damage_type: DamageType) -> Self {
+        Damage {
+            amount,
+            damage_type,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Health { current: max, max }
+    }
+
+    /// Removes hit points and returns how many were actually lost.
+    pub fn take(&mut self, amount: f32) -> f32 {
+        let lost = amount.max(0.0).min(self.current);
+        self.current -= lost;
+        lost
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount.max(0.0)).min(self.max);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.max > 0.0 {
+            self.current / self.max
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Fraction of the damage of each type that is ignored, from 0 for none to 1 for immune.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Resistances {
+    pub kinetic: f32,
+    pub explosive: f32,
+    pub energy: f32,
+}
+
+impl Resistances {
+    pub fn get(&self, damage_type: DamageType) -> f32 {
+        match damage_type {
+            DamageType::Kinetic => self.kinetic,
+            DamageType::Explosive => self.explosive,
+            DamageType::Energy => self.energy,
+        }
+    }
+
+    /// Returns the damage left after resistances.
+    pub fn apply(&self, damage: Damage) -> f32 {
+        damage.amount.max(0.0) * (1.0 - self.get(damage.damage_type).clamp(0.0, 1.0))
+    }
+}
+
+/// Marks an entity whose health reached zero, so it is only destroyed once.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Destroyed;
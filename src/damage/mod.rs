@@ -0,0 +1,302 @@
+pub mod armour;
+pub mod debris;
+pub mod event;
+pub mod health;
+pub mod label;
+pub mod shield;
+
+use bevy::prelude::*;
+
+use crate::projectile::{event::ProjectileHitEvent, label::ProjectileSystem};
+
+use self::{
+    armour::Armour,
+    debris::{spawn_debris, update_debris, Debris},
+    event::{DamageEvent, DestroyedEvent},
+    health::{Damage, Destroyed, Health, Resistances},
+    label::DamageSystem,
+    shield::{recharge_shields, Shield},
+};
+
+/// Turns projectile hits into [DamageEvent]s, applies them to [Health] and despawns the entities
+/// whose health runs out. Needs no rendering, so it also runs with the minimal plugins.
+#[derive(Default)]
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<Events<ProjectileHitEvent>>() {
+            app.add_event::<ProjectileHitEvent>();
+        }
+        app.add_event::<DamageEvent>()
+            .add_event::<DestroyedEvent>()
+            .add_system(
+                apply_projectile_hits
+                    .label(DamageSystem::ApplyProjectileHits)
+                    .after(ProjectileSystem::DetectHits),
+            )
+            .add_system(
+                apply_damage
+                    .label(DamageSystem::ApplyDamage)
+                    .after(DamageSystem::ApplyProjectileHits),
+            )
+            .add_system(
+                destroy_entities
+                    .label(DamageSystem::Destroy)
+                    .after(DamageSystem::ApplyDamage),
+            )
+            .add_system(recharge_shields.before(DamageSystem::ApplyDamage))
+            .add_system(update_debris);
+    }
+}
+
+fn apply_projectile_hits(
+    mut hit_events: EventReader<ProjectileHitEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for event in hit_events.iter() {
+        damage_events.send(DamageEvent {
+            target: event.target,
+            source: Some(event.projectile),
+            source_generation: event.generation,
+            damage: event.damage,
+            position: event.intersection.position,
+            normal: Some(event.intersection.normal),
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut destroyed_events: EventWriter<DestroyedEvent>,
+    mut query: Query<
+        (
+            &mut Health,
+            Option<&mut Shield>,
+            Option<&Armour>,
+            Option<&Resistances>,
+            Option<&GlobalTransform>,
+        ),
+        Without<Destroyed>,
+    >,
+    health_query: Query<(), With<Health>>,
+    parent_query: Query<&Parent>,
+) {
+    for event in damage_events.iter() {
+        let target = match health_owner(event.target, &health_query, &parent_query) {
+            Some(target) => target,
+            None => continue,
+        };
+        let (mut health, mut shield, armour, resistances, transform) = match query.get_mut(target) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        // Already killed by an earlier event of this frame
+        if health.is_dead() {
+            continue;
+        }
+        let amount = resolve_damage(
+            event.damage,
+            event.normal,
+            shield.as_deref_mut(),
+            armour,
+            resistances,
+            transform,
+        );
+        health.take(amount);
+        if health.is_dead() {
+            commands.entity(target).insert(Destroyed);
+            destroyed_events.send(DestroyedEvent {
+                entity: target,
+                source: event.source,
+                source_generation: event.source_generation,
+                position: transform.map_or(event.position, |transform| transform.translation),
+            });
+        }
+    }
+}
+
+/// Returns the entity or its closest ancestor with [Health], e.g. the ship owning the mesh that was
+/// hit.
+fn health_owner(
+    entity: Entity,
+    health_query: &Query<(), With<Health>>,
+    parent_query: &Query<&Parent>,
+) -> Option<Entity> {
+    let mut entity = entity;
+    while health_query.get(entity).is_err() {
+        entity = parent_query.get(entity).ok()?.0;
+    }
+    Some(entity)
+}
+
+/// Returns the damage reaching health. The shield absorbs what it can first, then the armour
+/// facing struck by the hit reduces the rest, and finally the resistances to the damage type.
+pub fn resolve_damage(
+    damage: Damage,
+    normal: Option<Vec3>,
+    shield: Option<&mut Shield>,
+    armour: Option<&Armour>,
+    resistances: Option<&Resistances>,
+    transform: Option<&GlobalTransform>,
+) -> f32 {
+    let mut amount = damage.amount.max(0.0);
+    if let Some(shield) = shield {
+        amount = shield.absorb(amount);
+    }
+    if let (Some(armour), Some(normal), Some(transform)) = (armour, normal, transform) {
+        amount = armour.apply(amount, normal, transform);
+    }
+    resistances
+        .copied()
+        .unwrap_or_default()
+        .apply(Damage::new(amount, damage.damage_type))
+}
+
+fn destroy_entities(
+    mut commands: Commands,
+    mut destroyed_events: EventReader<DestroyedEvent>,
+    query: Query<(Option<&Debris>, Option<&GlobalTransform>)>,
+) {
+    for event in destroyed_events.iter() {
+        if let Ok((debris, transform)) = query.get(event.entity) {
+            if let (Some(debris), Some(transform)) = (debris, transform) {
+                spawn_debris(&mut commands, debris, transform);
+            }
+            commands.entity(event.entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{health::DamageType, *};
+
+    #[test]
+    fn shield_then_armour_then_resistances() {
+        let mut shield = Shield::new(30.0, 0.0, 0.0);
+        let armour = Armour {
+            front: 0.5,
+            ..Default::default()
+        };
+        let resistances = Resistances {
+            kinetic: 0.2,
+            ..Default::default()
+        };
+        // Struck on the front, which faces -Z
+        let amount = resolve_damage(
+            Damage::new(100.0, DamageType::Kinetic),
+            Some(-Vec3::Z),
+            Some(&mut shield),
+            Some(&armour),
+            Some(&resistances),
+            Some(&GlobalTransform::identity()),
+        );
+        // The shield takes the first 30, the armour halves the rest and resistances take a fifth
+        assert!((amount - 28.0).abs() < 1e-4, "{}", amount);
+        assert!(shield.is_depleted());
+
+        // Without a normal the armour facing is unknown and ignored
+        let amount = resolve_damage(
+            Damage::new(100.0, DamageType::Energy),
+            None,
+            None,
+            Some(&armour),
+            Some(&resistances),
+            Some(&GlobalTransform::identity()),
+        );
+        assert!((amount - 100.0).abs() < 1e-4, "{}", amount);
+    }
+
+    #[test]
+    fn health_is_clamped() {
+        let mut health = Health::new(50.0);
+        assert_eq!(health.take(-10.0), 0.0);
+        assert_eq!(health.take(80.0), 50.0);
+        assert_eq!(health.current, 0.0);
+        assert!(health.is_dead());
+        health.heal(80.0);
+        assert_eq!(health.current, 50.0);
+    }
+
+    #[test]
+    fn destroyed_event_sent_once() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugin(DamagePlugin);
+        let target = app.world.spawn().insert(Health::new(50.0)).id();
+        let damage = DamageEvent {
+            target,
+            source: None,
+            source_generation: 0,
+            damage: Damage::new(40.0, DamageType::Kinetic),
+            position: Vec3::ZERO,
+            normal: None,
+        };
+
+        let mut destroyed_reader = app
+            .world
+            .get_resource::<Events<DestroyedEvent>>()
+            .unwrap()
+            .get_reader();
+        let mut destroyed_count = 0;
+        // Overkill within a frame, then more hits on the following frames
+        for hits in [3, 1, 1] {
+            let mut damage_events = app.world.get_resource_mut::<Events<DamageEvent>>().unwrap();
+            for _ in 0..hits {
+                damage_events.send(damage);
+            }
+            app.update();
+            let destroyed_events = app.world.get_resource::<Events<DestroyedEvent>>().unwrap();
+            destroyed_count += destroyed_reader.iter(destroyed_events).count();
+        }
+        assert_eq!(destroyed_count, 1);
+        assert!(app.world.get_entity(target).is_none());
+    }
+
+    #[test]
+    fn damage_to_mesh_reaches_parent_health() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugin(DamagePlugin);
+        let mesh = app.world.spawn().id();
+        let ship = app
+            .world
+            .spawn()
+            .insert(Health::new(50.0))
+            .push_children(&[mesh])
+            .id();
+
+        let mut destroyed_reader = app
+            .world
+            .get_resource::<Events<DestroyedEvent>>()
+            .unwrap()
+            .get_reader();
+        let damage = DamageEvent {
+            target: mesh,
+            source: None,
+            source_generation: 0,
+            damage: Damage::new(30.0, DamageType::Kinetic),
+            position: Vec3::ZERO,
+            normal: None,
+        };
+        let mut hit = |app: &mut App| {
+            app.world
+                .get_resource_mut::<Events<DamageEvent>>()
+                .unwrap()
+                .send(damage);
+            app.update();
+        };
+        hit(&mut app);
+        assert_eq!(app.world.get::<Health>(ship).unwrap().current, 20.0);
+        hit(&mut app);
+        let destroyed_events = app.world.get_resource::<Events<DestroyedEvent>>().unwrap();
+        let destroyed: Vec<_> = destroyed_reader
+            .iter(destroyed_events)
+            .map(|event| event.entity)
+            .collect();
+        assert_eq!(destroyed, vec![ship]);
+        assert!(app.world.get_entity(ship).is_none());
+        assert!(app.world.get_entity(mesh).is_none());
+    }
+}
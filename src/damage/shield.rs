@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+
+/// Regenerating shield that absorbs damage before it reaches armour and health.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Shield {
+    pub current: f32,
+    pub capacity: f32,
+    /// Points regained per second once recharging.
+    pub recharge_rate: f32,
+    /// Seconds after the last hit before the shield starts recharging.
+    pub recharge_delay: f32,
+    /// Seconds since the shield last absorbed damage.
+    pub since_last_hit: f32,
+}
+
+impl Shield {
+    pub fn new(capacity: f32, recharge_rate: f32, recharge_delay: f32) -> Self {
+        Shield {
+            current: capacity,
+            capacity,
+            recharge_rate,
+            recharge_delay,
+            since_last_hit: recharge_delay,
+        }
+    }
+
+    /// Absorbs as much of the damage as possible and returns what is left of it.
+    pub fn absorb(&mut self, amount: f32) -> f32 {
+        let absorbed = amount.max(0.0).min(self.current);
+        self.current -= absorbed;
+        self.since_last_hit = 0.0;
+        amount - absorbed
+    }
+
+    pub fn recharge(&mut self, seconds: f32) {
+        self.since_last_hit += seconds;
+        if self.since_last_hit >= self.recharge_delay {
+            self.current = (self.current + self.recharge_rate * seconds).min(self.capacity);
+        }
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+pub fn recharge_shields(time: Res<Time>, mut query: Query<&mut Shield>) {
+    for mut shield in query.iter_mut() {
+        shield.recharge(time.delta_seconds());
+    }
+}
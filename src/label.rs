@@ -1 +1,3 @@
 pub const HANDLE_INPUT_SYSTEM: &str = "handle_input_system";
+pub const FIRE_INPUT_SYSTEM: &str = "fire_input_system";
+pub const LOCK_ON_INPUT_SYSTEM: &str = "lock_on_input_system";
@@ -1,5 +1,6 @@
 pub mod camera;
 pub mod controller;
+pub mod damage;
 pub mod fps;
 pub mod label;
 pub mod lock_on;
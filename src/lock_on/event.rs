@@ -1,7 +1,16 @@
 use bevy::prelude::Entity;
 
+/// Sent when the lock of the `source` entity, e.g. a camera, is attached to a target or released.
 #[derive(Debug)]
 pub enum LockOnEvent {
-    Attached(Entity),
-    Released,
+    Attached { source: Entity, target: Entity },
+    Released { source: Entity },
+}
+
+/// Asks the [RayCastSource](crate::raycast::RayCastSource) entity, e.g. a camera, to lock on to the
+/// target nearest its aim, or to release its lock. Sent by player input, AI or networking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockOnCommand {
+    Acquire(Entity),
+    Release(Entity),
 }
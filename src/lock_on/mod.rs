@@ -1,38 +1,120 @@
 pub mod event;
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 
 use crate::{
-    raycast::{RayCastMesh, RayCastSource},
-    tag::MyRaycastSet,
+    camera::tag::CameraTag,
+    raycast::{Cone, RayCastMesh, RayCastSource, RayCastSources, RayCaster},
+    tag::{MyRaycastSet, PlayerModelTag},
+    LOCK_ON_INPUT_SYSTEM,
 };
 
-use self::event::LockOnEvent;
+use self::event::{LockOnCommand, LockOnEvent};
+
+/// Half-angle of the reticle circle in which targets can be locked on to.
+const LOCK_ON_ANGLE: f32 = 5.0 * std::f32::consts::PI / 180.0;
+const LOCK_ON_RANGE: f32 = 10_000.0;
 
 pub struct LockOnPlugin;
 
 impl Plugin for LockOnPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<LockOnEvent>().add_system(handle_lock_on);
+        app.add_event::<LockOnEvent>()
+            .add_event::<LockOnCommand>()
+            .add_system(keyboard_lock_on_commands.label(LOCK_ON_INPUT_SYSTEM))
+            .add_system(handle_lock_on.after(LOCK_ON_INPUT_SYSTEM));
     }
 }
 
-fn handle_lock_on(
+/// Maps right control to acquiring a lock and O to releasing it, for every camera with a source.
+fn keyboard_lock_on_commands(
     keys: Res<Input<KeyCode>>,
-    query: Query<&mut RayCastSource<MyRaycastSet>>,
+    camera_query: Query<Entity, (With<CameraTag>, With<RayCastSource<MyRaycastSet>>)>,
+    mut lock_on_commands: EventWriter<LockOnCommand>,
+) {
+    for camera in camera_query.iter() {
+        if keys.pressed(KeyCode::RControl) {
+            lock_on_commands.send(LockOnCommand::Acquire(camera));
+        }
+        if keys.pressed(KeyCode::O) {
+            lock_on_commands.send(LockOnCommand::Release(camera));
+        }
+    }
+}
+
+fn handle_lock_on(
+    mut lock_on_commands: EventReader<LockOnCommand>,
+    sources: RayCastSources<MyRaycastSet>,
+    ray_caster: RayCaster<MyRaycastSet>,
     mut lock_on_events_writer: EventWriter<LockOnEvent>,
+    mut locked_targets: Local<HashMap<Entity, Entity>>,
+    player_query: Query<Entity, With<PlayerModelTag>>,
     // mut raycast_meshes: Query<(&Name, &mut Visibility), With<RayCastMesh<MyRaycastSet>>>,
 ) {
-    if keys.pressed(KeyCode::RControl) {
-        let source = query.single();
-        if let Some((entity, _)) = source.intersections.iter().next() {
-            lock_on_events_writer.send(LockOnEvent::Attached(*entity));
-            // if let Ok((name, _)) = raycast_meshes.get_mut(*entity) {
-            //     println!("Lock-on to {}!", name.as_str());
-            // }
+    // The camera sits behind the player ship, which must not hide the targets in front of it
+    let player_ships: Vec<Entity> = player_query.iter().collect();
+    let has_line_of_sight = |source: &RayCastSource<MyRaycastSet>, origin: Vec3, target: Entity| {
+        let mut ignore = player_ships.clone();
+        ignore.push(target);
+        ray_caster
+            .transform(target)
+            .map(|transform| {
+                ray_caster.line_of_sight(origin, transform.translation, &ignore, &source.filter())
+            })
+            .unwrap_or(false)
+    };
+
+    // Release the locks whose target hides behind cover
+    locked_targets.retain(|source_entity, target| {
+        let source = match sources.source(*source_entity) {
+            Some(source) => source,
+            None => return false,
+        };
+        match source.ray {
+            Some(ray) if !has_line_of_sight(source, ray.origin(), *target) => {
+                lock_on_events_writer.send(LockOnEvent::Released {
+                    source: *source_entity,
+                });
+                false
+            }
+            _ => true,
+        }
+    });
+
+    // Every source locks on through its own crosshair
+    for command in lock_on_commands.iter() {
+        match *command {
+            LockOnCommand::Acquire(source_entity) => {
+                let source = match sources.source(source_entity) {
+                    Some(source) => source,
+                    None => continue,
+                };
+                let target = source.ray.and_then(|ray| {
+                    let cone =
+                        Cone::new(ray.origin(), ray.direction(), LOCK_ON_ANGLE, LOCK_ON_RANGE);
+                    ray_caster
+                        .cone_query(&cone, &source.filter())
+                        .into_iter()
+                        .map(|(entity, _)| entity)
+                        .find(|entity| has_line_of_sight(source, ray.origin(), *entity))
+                });
+                if let Some(entity) = target {
+                    locked_targets.insert(source_entity, entity);
+                    lock_on_events_writer.send(LockOnEvent::Attached {
+                        source: source_entity,
+                        target: entity,
+                    });
+                    // if let Ok((name, _)) = raycast_meshes.get_mut(*entity) {
+                    //     println!("Lock-on to {}!", name.as_str());
+                    // }
+                }
+            }
+            LockOnCommand::Release(source_entity) => {
+                locked_targets.remove(&source_entity);
+                lock_on_events_writer.send(LockOnEvent::Released {
+                    source: source_entity,
+                });
+            }
         }
-    }
-    if keys.pressed(KeyCode::O) {
-        lock_on_events_writer.send(LockOnEvent::Released);
     }
 }
@@ -0,0 +1,209 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Deserialize;
+
+use super::{event::DeployCountermeasureCommand, seeker::Decoy};
+
+const DEFAULT_SEED: u64 = 0x5eed;
+const DEFAULT_FLARE: &str = "countermeasures/flare.countermeasure.ron";
+const DEFAULT_CHAFF: &str = "countermeasures/chaff.countermeasure.ron";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum CountermeasureKind {
+    /// Hot burning decoy, fools infrared seekers.
+    Flare,
+    /// Cloud of reflective strips, fools radar seekers.
+    Chaff,
+}
+
+/// A countermeasure loaded from a `.countermeasure.ron` file.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, TypeUuid)]
+#[uuid = "d0761829-1100-4595-9455-ff09e3929b5c"]
+pub struct CountermeasureDefinition {
+    pub kind: CountermeasureKind,
+    /// Chance from 0 to 1 of pulling an unresisting seeker off its target, when fresh.
+    pub strength: f32,
+    /// Seconds before the decoy burns out.
+    pub lifetime: f32,
+    /// Speed the decoy is ejected at, backwards from the deployer.
+    pub ejection_speed: f32,
+    /// Fraction of the speed lost per second.
+    pub drag: f32,
+    /// sRGB colour.
+    pub color: [f32; 3],
+}
+
+#[derive(Default)]
+pub struct CountermeasureDefinitionLoader;
+
+impl AssetLoader for CountermeasureDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let definition: CountermeasureDefinition = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(definition));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["countermeasure.ron"]
+    }
+}
+
+/// Definition deployed for each kind of countermeasure.
+pub struct CountermeasureDefinitions(
+    pub HashMap<CountermeasureKind, Handle<CountermeasureDefinition>>,
+);
+
+impl FromWorld for CountermeasureDefinitions {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+        let mut definitions = HashMap::default();
+        definitions.insert(CountermeasureKind::Flare, asset_server.load(DEFAULT_FLARE));
+        definitions.insert(CountermeasureKind::Chaff, asset_server.load(DEFAULT_CHAFF));
+        CountermeasureDefinitions(definitions)
+    }
+}
+
+/// A decoy deployed to pull missiles off their target.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Countermeasure {
+    pub kind: CountermeasureKind,
+    /// Chance from 0 to 1 of pulling an unresisting seeker off its target, when fresh.
+    pub strength: f32,
+    /// Seconds before the decoy burns out.
+    pub lifetime: f32,
+    pub age: f32,
+    pub velocity: Vec3,
+    /// Fraction of the speed lost per second.
+    pub drag: f32,
+}
+
+impl Countermeasure {
+    pub fn new(definition: &CountermeasureDefinition, velocity: Vec3) -> Self {
+        Countermeasure {
+            kind: definition.kind,
+            strength: definition.strength,
+            lifetime: definition.lifetime,
+            age: 0.0,
+            velocity,
+            drag: definition.drag,
+        }
+    }
+
+    /// Strength fading out over the lifetime of the decoy.
+    pub fn current_strength(&self) -> f32 {
+        self.strength * (1.0 - self.age / self.lifetime).max(0.0)
+    }
+
+    pub fn is_burnt_out(&self) -> bool {
+        self.age >= self.lifetime
+    }
+
+    pub fn decoy(&self, entity: Entity, position: Vec3) -> Decoy {
+        Decoy {
+            entity,
+            position,
+            kind: self.kind,
+            strength: self.current_strength(),
+        }
+    }
+}
+
+/// Random numbers of the seekers rolling against decoys. Seeded, so a replay of the same inputs
+/// gives the same outcomes.
+pub struct CountermeasureRng(pub StdRng);
+
+impl CountermeasureRng {
+    pub fn from_seed(seed: u64) -> Self {
+        CountermeasureRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for CountermeasureRng {
+    fn default() -> Self {
+        CountermeasureRng::from_seed(DEFAULT_SEED)
+    }
+}
+
+/// Ejects a decoy behind the deployer for every [DeployCountermeasureCommand]. Commands for a kind
+/// whose definition is not loaded yet are dropped.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn deploy_countermeasures(
+    mut commands: Commands,
+    definitions: Res<CountermeasureDefinitions>,
+    definition_assets: Res<Assets<CountermeasureDefinition>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut visuals: Local<HashMap<CountermeasureKind, (Handle<Mesh>, Handle<StandardMaterial>)>>,
+    mut deploy_commands: EventReader<DeployCountermeasureCommand>,
+    deployer_query: Query<&GlobalTransform>,
+) {
+    for command in deploy_commands.iter() {
+        let deployer_transform = match deployer_query.get(command.deployer) {
+            Ok(transform) => transform,
+            Err(_) => continue,
+        };
+        let definition = match definitions
+            .0
+            .get(&command.kind)
+            .and_then(|handle| definition_assets.get(handle))
+        {
+            Some(definition) => definition,
+            None => continue,
+        };
+        let (mesh, material) = visuals
+            .entry(command.kind)
+            .or_insert_with(|| {
+                let mesh = meshes.add(Mesh::from(shape::Icosphere {
+                    radius: 0.5,
+                    subdivisions: 1,
+                }));
+                let material = materials.add(StandardMaterial {
+                    base_color: Color::from(definition.color),
+                    unlit: true,
+                    ..Default::default()
+                });
+                (mesh, material)
+            })
+            .clone();
+
+        let velocity = deployer_transform.rotation * Vec3::Z * definition.ejection_speed;
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh,
+                material,
+                transform: Transform::from_translation(deployer_transform.translation),
+                ..Default::default()
+            })
+            .insert(Name::new(format!("{:?}", command.kind)))
+            .insert(Countermeasure::new(definition, velocity));
+    }
+}
+
+pub(crate) fn update_countermeasures(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &mut Countermeasure, Entity)>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (mut transform, mut countermeasure, entity) in query.iter_mut() {
+        countermeasure.age += delta_seconds;
+        if countermeasure.is_burnt_out() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let drag = (1.0 - countermeasure.drag * delta_seconds).max(0.0);
+        countermeasure.velocity *= drag;
+        transform.translation += countermeasure.velocity * delta_seconds;
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::{
+    damage::health::Damage,
+    raycast::{primitives::Intersection, ray::Ray3d},
+};
+
+use super::{countermeasure::CountermeasureKind, motor::SelfDestructReason, weapon::WeaponGroup};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectileHitKind {
+    /// The projectile struck a triangle of the target mesh.
+    Impact,
+    /// A ballistic projectile detonated close to the target without touching it.
+    Proximity,
+}
+
+/// Sent once for every entity struck by a projectile.
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectileHitEvent {
+    /// Pooled projectiles keep their entity when fired again, so this may later name another shot
+    /// of the same weapon, told apart by `generation`.
+    pub projectile: Entity,
+    /// [PooledProjectile::generation](super::pool::PooledProjectile::generation) of the
+    /// projectile, or 0 if it is not pooled.
+    pub generation: u32,
+    pub target: Entity,
+    /// World space hit position and surface normal. Missile proximity hits report the surface of
+    /// the target facing the missile, or the missile position with a normal pointing from the
+    /// target towards it if no surface of the target lies in between.
+    pub intersection: Intersection,
+    /// Velocity of the projectile at the time of the hit.
+    pub velocity: Vec3,
+    /// Damage carried by the projectile, before the resistances of the target.
+    pub damage: Damage,
+    pub kind: ProjectileHitKind,
+}
+
+/// Fires the weapons of a group of the shooter, an entity with
+/// [Weapons](super::weapon::Weapons). Any system can send it, e.g. player input, AI or networking.
+/// Weapons still cooling down ignore it.
+#[derive(Debug, Clone, Copy)]
+pub struct FireCommand {
+    pub shooter: Entity,
+    pub group: WeaponGroup,
+    /// World space ray the shooter aims along.
+    pub aim: Ray3d,
+    /// Point the unguided projectiles converge on, e.g. what is under the crosshair.
+    pub aim_point: Option<Vec3>,
+    /// Entity guided projectiles home on.
+    pub target: Option<Entity>,
+}
+
+/// Sent when a missile blows itself up without hitting anything.
+#[derive(Debug, Clone, Copy)]
+pub struct MissileSelfDestructEvent {
+    pub missile: Entity,
+    /// [PooledProjectile::generation](super::pool::PooledProjectile::generation) of the missile,
+    /// or 0 if it is not pooled.
+    pub generation: u32,
+    pub position: Vec3,
+    pub reason: SelfDestructReason,
+}
+
+/// Ejects a decoy from the deployer, an entity with a [GlobalTransform].
+#[derive(Debug, Clone, Copy)]
+pub struct DeployCountermeasureCommand {
+    pub deployer: Entity,
+    pub kind: CountermeasureKind,
+}
@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::seeker::SeekerParams;
+
+/// How a guided projectile steers towards its target.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum GuidanceLaw {
+    /// Points straight at the current position of the target.
+    PurePursuit,
+    /// Points at where the target will be, from its velocity, when the projectile gets there.
+    LeadPursuit,
+    /// Turns proportionally to the rotation rate of the line of sight to the target.
+    ProportionalNavigation { gain: f32 },
+    /// Proportional navigation that also compensates for the acceleration of the target.
+    AugmentedProportionalNavigation { gain: f32 },
+}
+
+impl Default for GuidanceLaw {
+    fn default() -> Self {
+        GuidanceLaw::ProportionalNavigation { gain: 4.0 }
+    }
+}
+
+/// Steering of guided projectiles. The default never turns, for unguided rockets.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+pub struct GuidanceParams {
+    #[serde(default)]
+    pub law: GuidanceLaw,
+    /// Fastest the heading can turn, in radians per second.
+    pub max_turn_rate: f32,
+    /// Highest lateral acceleration the airframe can pull, in units per second squared.
+    pub max_acceleration: f32,
+    /// Distance to the target at which the warhead detonates.
+    pub fuse_radius: f32,
+    #[serde(default)]
+    pub seeker: SeekerParams,
+}
+
+/// Positions and velocities seen by the guidance of a projectile during one tick.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Engagement {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub target_position: Vec3,
+    pub target_velocity: Vec3,
+    pub target_acceleration: Vec3,
+}
+
+impl Engagement {
+    pub fn relative_position(&self) -> Vec3 {
+        self.target_position - self.position
+    }
+
+    pub fn relative_velocity(&self) -> Vec3 {
+        self.target_velocity - self.velocity
+    }
+
+    /// Rate at which the distance to the target shrinks.
+    pub fn closing_speed(&self) -> f32 {
+        -self
+            .relative_position()
+            .normalize_or_zero()
+            .dot(self.relative_velocity())
+    }
+
+    /// Rotation rate of the line of sight, as an angular velocity vector.
+    pub fn line_of_sight_rate(&self) -> Vec3 {
+        let range_squared = self.relative_position().length_squared();
+        if range_squared <= f32::EPSILON {
+            return Vec3::ZERO;
+        }
+        self.relative_position().cross(self.relative_velocity()) / range_squared
+    }
+
+    /// Point where a projectile flying straight at its current speed meets the target, assuming
+    /// the target keeps its velocity.
+    pub fn intercept_point(&self) -> Option<Vec3> {
+        let offset = self.relative_position();
+        let speed = self.velocity.length();
+        let a = self.target_velocity.length_squared() - speed * speed;
+        let b = 2.0 * offset.dot(self.target_velocity);
+        let c = offset.length_squared();
+        let time = if a.abs() <= f32::EPSILON {
+            if b >= 0.0 {
+                return None;
+            }
+            -c / b
+        } else {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let root = discriminant.sqrt();
+            let (t0, t1) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+            match (t0.min(t1), t0.max(t1)) {
+                (near, _) if near > 0.0 => near,
+                (_, far) if far > 0.0 => far,
+                _ => return None,
+            }
+        };
+        Some(self.target_position + self.target_velocity * time)
+    }
+}
+
+/// Lateral acceleration commanded by the law, perpendicular to the heading.
+pub fn commanded_acceleration(
+    law: GuidanceLaw,
+    engagement: &Engagement,
+    delta_seconds: f32,
+) -> Vec3 {
+    let heading = engagement.velocity.normalize_or_zero();
+    let acceleration = match law {
+        GuidanceLaw::PurePursuit => {
+            pursuit_acceleration(engagement, engagement.relative_position(), delta_seconds)
+        }
+        GuidanceLaw::LeadPursuit => {
+            let aim = engagement
+                .intercept_point()
+                .unwrap_or(engagement.target_position);
+            pursuit_acceleration(engagement, aim - engagement.position, delta_seconds)
+        }
+        GuidanceLaw::ProportionalNavigation { gain } => proportional_navigation(gain, engagement),
+        GuidanceLaw::AugmentedProportionalNavigation { gain } => {
+            let line_of_sight = engagement.relative_position().normalize_or_zero();
+            let target_acceleration = engagement.target_acceleration
+                - line_of_sight * line_of_sight.dot(engagement.target_acceleration);
+            proportional_navigation(gain, engagement) + target_acceleration * gain / 2.0
+        }
+    };
+    acceleration - heading * heading.dot(acceleration)
+}
+
+/// Acceleration turning the heading onto `to_aim` within one tick.
+fn pursuit_acceleration(engagement: &Engagement, to_aim: Vec3, delta_seconds: f32) -> Vec3 {
+    let heading = engagement.velocity.normalize_or_zero();
+    let desired = to_aim.normalize_or_zero();
+    let lateral = (desired - heading * heading.dot(desired)).normalize_or_zero();
+    let angle = heading.angle_between(desired);
+    if !angle.is_finite() || delta_seconds <= 0.0 {
+        return Vec3::ZERO;
+    }
+    lateral * engagement.velocity.length().max(1.0) * angle / delta_seconds
+}
+
+/// True proportional navigation, `gain * closing speed * line of sight rate`, perpendicular to the
+/// line of sight.
+fn proportional_navigation(gain: f32, engagement: &Engagement) -> Vec3 {
+    let line_of_sight = engagement.relative_position().normalize_or_zero();
+    gain * engagement.closing_speed() * engagement.line_of_sight_rate().cross(line_of_sight)
+}
+
+/// Turns the heading by the lateral acceleration over one tick, within the turn rate and
+/// acceleration limits of the airframe.
+pub fn steer(
+    heading: Vec3,
+    speed: f32,
+    acceleration: Vec3,
+    params: &GuidanceParams,
+    delta_seconds: f32,
+) -> Vec3 {
+    let lateral = (acceleration - heading * heading.dot(acceleration))
+        .clamp_length_max(params.max_acceleration);
+    if lateral.length_squared() <= f32::EPSILON {
+        return heading;
+    }
+    let turn_rate = if speed > f32::EPSILON {
+        (lateral.length() / speed).min(params.max_turn_rate)
+    } else {
+        params.max_turn_rate
+    };
+    let axis = heading.cross(lateral).normalize_or_zero();
+    if axis == Vec3::ZERO {
+        return heading;
+    }
+    (Quat::from_axis_angle(axis, turn_rate * delta_seconds) * heading).normalize()
+}
+
+/// Returns true if the target comes within the fuse radius during the next tick, or is already
+/// inside it and no longer getting closer.
+pub fn fuse_triggered(engagement: &Engagement, fuse_radius: f32, delta_seconds: f32) -> bool {
+    let offset = engagement.relative_position();
+    let relative_velocity = engagement.relative_velocity();
+    if offset.length() <= fuse_radius && engagement.closing_speed() <= 0.0 {
+        return true;
+    }
+    let relative_speed_squared = relative_velocity.length_squared();
+    if relative_speed_squared <= f32::EPSILON {
+        return offset.length() <= fuse_radius;
+    }
+    let closest_time =
+        (-offset.dot(relative_velocity) / relative_speed_squared).clamp(0.0, delta_seconds);
+    (offset + relative_velocity * closest_time).length() <= fuse_radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA_SECONDS: f32 = 1.0 / 60.0;
+    const FLIGHT_TIME: f32 = 10.0;
+    const MISSILE_SPEED: f32 = 60.0;
+
+    /// Flies a missile against a target circling like the one in the `moving-target` example,
+    /// and returns the time of the intercept, or the closest the missile got to the target.
+    fn fly(law: GuidanceLaw) -> Result<f32, f32> {
+        let params = GuidanceParams {
+            law,
+            max_turn_rate: 2.0,
+            max_acceleration: 120.0,
+            fuse_radius: 2.0,
+            seeker: Default::default(),
+        };
+        let mut position = Vec3::ZERO;
+        let mut heading = -Vec3::Z;
+        let speed = MISSILE_SPEED;
+        let mut target_position = Vec3::new(0.0, 0.0, -200.0);
+        let mut previous_target_velocity = None;
+        let mut miss_distance = f32::MAX;
+
+        let mut time = 0.0;
+        while time < FLIGHT_TIME {
+            let target_velocity = Vec3::new(time.cos() * 30.0, time.sin() * 30.0, 0.0);
+            let target_acceleration = previous_target_velocity
+                .map(|previous| (target_velocity - previous) / DELTA_SECONDS)
+                .unwrap_or(Vec3::ZERO);
+            previous_target_velocity = Some(target_velocity);
+
+            let engagement = Engagement {
+                position,
+                velocity: heading * speed,
+                target_position,
+                target_velocity,
+                target_acceleration,
+            };
+            let acceleration = commanded_acceleration(params.law, &engagement, DELTA_SECONDS);
+            heading = steer(heading, speed, acceleration, &params, DELTA_SECONDS);
+
+            let engagement = Engagement {
+                velocity: heading * speed,
+                ..engagement
+            };
+            miss_distance = miss_distance.min(engagement.relative_position().length());
+            if fuse_triggered(&engagement, params.fuse_radius, DELTA_SECONDS) {
+                return Ok(time);
+            }
+
+            position += heading * speed * DELTA_SECONDS;
+            target_position += target_velocity * DELTA_SECONDS;
+            time += DELTA_SECONDS;
+        }
+        Err(miss_distance)
+    }
+
+    #[test]
+    fn pure_pursuit_misses_manoeuvring_target() {
+        let result = fly(GuidanceLaw::PurePursuit);
+        assert!(result.is_err(), "{:?}", result);
+    }
+
+    #[test]
+    fn proportional_navigation_intercepts_manoeuvring_target() {
+        let result = fly(GuidanceLaw::ProportionalNavigation { gain: 4.0 });
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[test]
+    fn augmented_proportional_navigation_intercepts_manoeuvring_target() {
+        let result = fly(GuidanceLaw::AugmentedProportionalNavigation { gain: 4.0 });
+        assert!(result.is_ok(), "{:?}", result);
+    }
+}
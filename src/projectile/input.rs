@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera::tag::CameraTag,
+    raycast::{ray::Ray3d, RayCastMesh, RayCastSources},
+    tag::{MyRaycastSet, PlayerModelTag},
+};
+
+use super::{
+    countermeasure::CountermeasureKind,
+    event::{DeployCountermeasureCommand, FireCommand},
+    weapon::{WeaponGroup, Weapons},
+};
+
+/// Turns the keyboard into [FireCommand]s for the player ship, aimed through the crosshair of the
+/// camera: left control fires the primary weapons and space the secondary ones.
+pub(crate) fn keyboard_fire_commands(
+    keys: Res<Input<KeyCode>>,
+    camera_query: Query<(&Transform, Entity), With<CameraTag>>,
+    ship_query: Query<Entity, (With<PlayerModelTag>, With<Weapons>)>,
+    sources: RayCastSources<MyRaycastSet>,
+    target_query: Query<Entity, With<RayCastMesh<MyRaycastSet>>>,
+    mut fire_commands: EventWriter<FireCommand>,
+) {
+    let mut groups = Vec::new();
+    if keys.pressed(KeyCode::LControl) {
+        groups.push(WeaponGroup::Primary);
+    }
+    if keys.just_pressed(KeyCode::Space) {
+        groups.push(WeaponGroup::Secondary);
+    }
+    if groups.is_empty() {
+        return;
+    }
+
+    let (camera_transform, camera) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let aim = Ray3d::from(camera_transform.compute_matrix());
+    // Aim at whatever is under the crosshair of this camera's source
+    let crosshair_hit = sources.hits(camera).and_then(|hits| hits.first());
+    let aim_point = crosshair_hit.map(|(_, intersection)| intersection.position);
+    // Home on the target under the crosshair, or on the only target around
+    let target = crosshair_hit
+        .map(|(entity, _)| *entity)
+        .or_else(|| target_query.get_single().ok());
+
+    for shooter in ship_query.iter() {
+        for group in groups.iter() {
+            fire_commands.send(FireCommand {
+                shooter,
+                group: *group,
+                aim,
+                aim_point,
+                target,
+            });
+        }
+    }
+}
+
+/// Turns the keyboard into [DeployCountermeasureCommand]s for the player ship: F ejects a flare
+/// and C chaff.
+pub(crate) fn keyboard_countermeasure_commands(
+    keys: Res<Input<KeyCode>>,
+    ship_query: Query<Entity, With<PlayerModelTag>>,
+    mut deploy_commands: EventWriter<DeployCountermeasureCommand>,
+) {
+    let mut kinds = Vec::new();
+    if keys.just_pressed(KeyCode::F) {
+        kinds.push(CountermeasureKind::Flare);
+    }
+    if keys.just_pressed(KeyCode::C) {
+        kinds.push(CountermeasureKind::Chaff);
+    }
+    for deployer in ship_query.iter() {
+        for kind in kinds.iter() {
+            deploy_commands.send(DeployCountermeasureCommand {
+                deployer,
+                kind: *kind,
+            });
+        }
+    }
+}
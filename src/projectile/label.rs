@@ -0,0 +1,8 @@
+use bevy::prelude::*;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum ProjectileSystem {
+    Fire,
+    Move,
+    DetectHits,
+}
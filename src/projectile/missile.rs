@@ -1,162 +1,264 @@
-use bevy::prelude::*;
-use lininterp::{InvLerp, Lerp};
+use bevy::{ecs::system::EntityCommands, prelude::*};
 
 use crate::{
-    camera::tag::CameraTag,
-    raycast::{ray::Ray3d, RayCastMesh, RayCastSource},
+    raycast::{primitives::Intersection, ray::Ray3d, RayCastFilter, RayCastMesh, RayCaster},
     tag::{MyRaycastSet, PlayerModelTag},
 };
 
 use super::{
-    projectile::Projectile, tag::ProjectileDetectableTag, MissileTimer, Target,
-    MAX_DISTANCE_SQUARED,
+    countermeasure::{Countermeasure, CountermeasureRng},
+    event::{MissileSelfDestructEvent, ProjectileHitEvent, ProjectileHitKind},
+    guidance::{commanded_acceleration, fuse_triggered, steer, Engagement, GuidanceParams},
+    motor::{Motor, SelfDestructReason},
+    pool::{PooledProjectile, ProjectilePool},
+    projectile::Projectile,
+    tag::ProjectileDetectableTag,
+    weapon::WeaponDefinition,
+    Target,
 };
 
-const MISSILE_SPEED: f32 = 0.0;
-const MISSILE_TURN_SPEED: f32 = 10.0;
-// const MAX_MISSILE_TARGETING_DISTANCE_SQUARED: f32 = MAX_DISTANCE_SQUARED / 4.0;
-
-// const maxDistancePredict: f32 = 100.0;
-// const minDistancePredict: f32 = 5.0;
-// const maxTimePrediction: f32 = 5.0;
-
 #[derive(Component)]
 pub(crate) struct Missile {
     pub target: Option<Entity>,
+    pub guidance: GuidanceParams,
+    /// Velocity of the target on the previous tick, to estimate its acceleration.
+    pub target_velocity: Option<Vec3>,
+    pub motor: Motor,
+    /// Decoys the seeker already rolled against.
+    pub seen_decoys: Vec<Entity>,
 }
 
-pub(crate) fn fire_missile(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    camera_query: Query<&Transform, With<CameraTag>>,
-    player_query: Query<&GlobalTransform, With<PlayerModelTag>>,
-    keys: Res<Input<KeyCode>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    source_query: Query<&RayCastSource<MyRaycastSet>>,
-    target_query: Query<Entity, With<RayCastMesh<MyRaycastSet>>>,
-    // time: Res<Time>,
-    // mut timer: ResMut<MissileTimer>,
+pub(crate) fn insert_missile(
+    projectile: &mut EntityCommands,
+    definition: &WeaponDefinition,
+    transform: Transform,
+    ray: Ray3d,
+    shooter: Entity,
+    target: Option<Entity>,
 ) {
-    if keys.just_pressed(KeyCode::Space) {
-        let spaceship_handle = asset_server.load("models/spaceship.gltf#Mesh0/Primitive0");
-
-        // if timer.0.tick(time.delta()).just_finished() {
-        let camera_transform = camera_query.single();
-        let camera_global_transform = player_query.single();
-        let ray = Ray3d::from(camera_transform.compute_matrix());
-
-        let cube_material_handle = materials.add(StandardMaterial {
-            base_color: Color::MIDNIGHT_BLUE,
-            reflectance: 0.5,
-            unlit: false,
-            ..Default::default()
-        });
+    let dir = ray.direction();
 
-        // let target = source_query
-        //     .single()
-        //     .intersections
-        //     .first()
-        //     .and_then(|(x, _)| Some(*x));
-
-        let target = Some(target_query.single());
-
-        let dir: Vec3 = ray.direction.into();
-
-        commands
-            .spawn_bundle(PbrBundle {
-                mesh: spaceship_handle.clone(),
-                material: cube_material_handle.clone(),
-                transform: Transform::from_translation(camera_global_transform.translation)
-                    .with_rotation(camera_transform.rotation)
-                    .with_scale(Vec3::new(1.4, 1.4, 5.0)),
-                ..Default::default()
-            })
-            .insert(Name::new("Missile"))
-            .insert(Projectile {
-                ballistic: true,
-                velocity: dir * MISSILE_SPEED,
-                direction: dir,
-                ray,
-                speed: MISSILE_SPEED,
-            })
-            .insert(Missile { target })
-            .insert(ProjectileDetectableTag);
-    }
-    // }
+    projectile
+        .insert(Name::new(definition.name.clone()))
+        .insert(Projectile {
+            ballistic: true,
+            velocity: dir * definition.speed,
+            direction: dir,
+            ray,
+            speed: definition.speed,
+            damage: definition.damage,
+            shooter,
+            origin: transform.translation,
+            previous_translation: transform.translation,
+            range: definition.range,
+        })
+        .insert(Missile {
+            target,
+            guidance: definition.guidance.unwrap_or_default(),
+            target_velocity: None,
+            motor: Motor::new(definition.motor),
+            seen_decoys: Vec::new(),
+        })
+        .insert(ProjectileDetectableTag);
 }
 
+/// Runs the motor of missiles, steers them with their guidance law and detonates them once their
+/// target passes within the fuse radius. Missiles that lose their target, run out of fuel or
+/// outlive their lifetime blow themselves up. Decoys entering the seeker cone may pull them off
+/// their target.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub(crate) fn update_missile(
     mut commands: Commands,
     time: Res<Time>,
+    mut hit_events: EventWriter<ProjectileHitEvent>,
+    mut self_destruct_events: EventWriter<MissileSelfDestructEvent>,
+    mut pool: ResMut<ProjectilePool>,
     mut projectile_query: Query<
-        (&mut Transform, &mut Projectile, &Missile, Entity),
+        (
+            &mut Transform,
+            &mut Projectile,
+            &mut Missile,
+            Option<&PooledProjectile>,
+            Entity,
+        ),
         Without<PlayerModelTag>,
     >,
-    player_query: Query<&GlobalTransform, With<PlayerModelTag>>,
     target_query: Query<(&GlobalTransform, &Target), With<RayCastMesh<MyRaycastSet>>>,
+    countermeasure_query: Query<(&GlobalTransform, &Countermeasure, Entity)>,
+    ray_caster: RayCaster<MyRaycastSet>,
+    mut rng: ResMut<CountermeasureRng>,
 ) {
-    let player_global_translation = player_query.single().translation;
-    for (mut transform, mut projectile, missile, entity) in projectile_query.iter_mut() {
-        if (transform.translation - player_global_translation).length_squared()
-            > MAX_DISTANCE_SQUARED
-        {
-            commands.entity(entity).despawn();
-        } else {
-            if let Some(target) = missile.target {
-                if let Ok((target_transform, target)) = target_query.get(target) {
-                    // let current_distance = (transform.translation - target_transform.translation).length();
-                    // let lead_time_percentage = minDistancePredict.inv_lerp(&maxTimePrediction, &current_distance);
-                    // let prediction_time = 0.0.lerp(&maxTimePrediction, lead_time_percentage);
-                    // let predicted_translation = target_transform.translation + (target.velocity * prediction_time);
-
-                    // let target_translation = predicted_translation;
-                    let target_translation = target_transform.translation;
-
-                    let looking_at_transform = transform.looking_at(target_translation, Vec3::Y);
-
-                    let rotation = looking_at_transform.rotation;
-
-                    transform.rotation = transform
-                        .rotation
-                        .slerp(rotation, MISSILE_TURN_SPEED * time.delta_seconds());
-
-                    let difference = target_translation - transform.translation;
-                    transform.translation +=
-                        projectile.speed * difference.normalize() * time.delta_seconds();
-
-                    let acceleration = 600.0;
-                    projectile.speed += acceleration * time.delta_seconds();
+    let delta_seconds = time.delta_seconds();
+    let decoys: Vec<_> = countermeasure_query
+        .iter()
+        .map(|(transform, countermeasure, entity)| {
+            countermeasure.decoy(entity, transform.translation)
+        })
+        .collect();
+    // Position and velocity of a target, which may be a decoy
+    let track = |target: Entity| {
+        target_query
+            .get(target)
+            .map(|(transform, target)| (transform.translation, target.velocity))
+            .or_else(|_| {
+                countermeasure_query
+                    .get(target)
+                    .map(|(transform, countermeasure, _)| {
+                        (transform.translation, countermeasure.velocity)
+                    })
+            })
+            .ok()
+    };
 
-                    // let difference = target_translation - transform.translation;
-                    // let target_direction = difference.normalize();
+    for (mut transform, mut projectile, mut missile, pooled, entity) in projectile_query.iter_mut()
+    {
+        if projectile.is_out_of_range(transform.translation) {
+            pool.release(&mut commands, entity, pooled);
+            continue;
+        }
+        projectile.previous_translation = transform.translation;
 
-                    // let acceleration =
-                    //     MISSILE_TURN_SPEED * 100.0 * m_direction * time.delta_seconds();
-                    // // println!("Velocity {} - Acceleration {}", projectile.velocity, acceleration);
+        // Let the seeker choose between the target and the decoys entering its cone
+        let seeker = missile.guidance.seeker;
+        let tracked = missile
+            .target
+            .and_then(|target| track(target).map(|(position, _)| (target, position)));
+        let seen = seeker.reacquire(
+            transform.translation,
+            transform.forward(),
+            tracked,
+            &decoys,
+            &mut missile.seen_decoys,
+            &mut rng.0,
+        );
 
-                    // projectile.velocity += acceleration;
-                    // transform.translation += projectile.velocity * time.delta_seconds();
+        // Lose track of targets hidden behind cover, out of the seeker cone or gone, and keep
+        // flying straight. The target is kept through the lock loss timeout, so the seeker picks it
+        // up again once it comes back into view
+        let target = seen
+            .filter(|target| {
+                ray_caster.entities_line_of_sight(entity, *target, &RayCastFilter::default())
+                    != Some(false)
+            })
+            .and_then(|target| {
+                track(target).map(|(position, velocity)| (target, position, velocity))
+            });
+        match target {
+            None => {
+                if missile.target.is_some() {
+                    missile.target_velocity = None;
+                    missile.motor.lose_lock();
+                }
+            }
+            Some((seen, _, _)) if missile.target != Some(seen) => {
+                missile.target = Some(seen);
+                missile.target_velocity = None;
+            }
+            Some(_) => {}
+        }
 
-                    // transform.translation += projectile.velocity * 5.0 * time.delta_seconds();
+        let target_distance =
+            target.map(|(_, target_position, _)| target_position.distance(transform.translation));
+        match missile
+            .motor
+            .update(projectile.speed, target_distance, delta_seconds)
+        {
+            Ok(speed) => projectile.speed = speed,
+            Err(reason) => {
+                if reason == SelfDestructReason::LostLock {
+                    missile.target = None;
+                }
+                self_destruct_events.send(MissileSelfDestructEvent {
+                    missile: entity,
+                    generation: pooled.map_or(0, |pooled| pooled.generation),
+                    position: transform.translation,
+                    reason,
+                });
+                pool.release(&mut commands, entity, pooled);
+                continue;
+            }
+        }
 
-                    // transform.translation +=
-                    //     MISSILE_SPEED * target_direction * time.delta_seconds();
+        let forward = transform.forward();
+        let mut heading = forward;
+        let target = target.filter(|_| missile.motor.is_guided());
+        if let Some((target, target_position, target_velocity)) = target {
+            let target_acceleration = match missile.target_velocity {
+                Some(previous) if delta_seconds > 0.0 => {
+                    (target_velocity - previous) / delta_seconds
+                }
+                _ => Vec3::ZERO,
+            };
+            missile.target_velocity = Some(target_velocity);
 
-                    // let difference = target_transform.translation - transform.translation;
-                    // let target_direction = difference.normalize();
-                    // transform.translation += MISSILE_TURN_SPEED * target_direction * time.delta_seconds();
+            let engagement = Engagement {
+                position: transform.translation,
+                velocity: heading * projectile.speed,
+                target_position,
+                target_velocity,
+                target_acceleration,
+            };
+            let acceleration =
+                commanded_acceleration(missile.guidance.law, &engagement, delta_seconds);
+            heading = steer(
+                heading,
+                projectile.speed,
+                acceleration,
+                &missile.guidance,
+                delta_seconds,
+            );
 
-                    // let target_distance = difference.length_squared();
-                    // // TODO Make missile stop targeting when angle between target and initial direction is too extreme
-                    // if target_distance < MAX_MISSILE_TARGETING_DISTANCE_SQUARED {
-                    //     let target_direction = difference.normalize();
-                    //     transform.translation += MISSILE_TURN_SPEED * target_direction * time.delta_seconds();
-                    // }
-                }
-            } else {
-                transform.translation += projectile.velocity * time.delta_seconds();
+            let engagement = Engagement {
+                velocity: heading * projectile.speed,
+                ..engagement
+            };
+            if fuse_triggered(&engagement, missile.guidance.fuse_radius, delta_seconds) {
+                hit_events.send(ProjectileHitEvent {
+                    projectile: entity,
+                    generation: pooled.map_or(0, |pooled| pooled.generation),
+                    target,
+                    intersection: proximity_intersection(
+                        &ray_caster,
+                        transform.translation,
+                        target,
+                        target_position,
+                    ),
+                    velocity: engagement.velocity,
+                    damage: projectile.damage,
+                    kind: ProjectileHitKind::Proximity,
+                });
+                pool.release(&mut commands, entity, pooled);
+                continue;
             }
         }
+
+        transform.rotate(Quat::from_rotation_arc(forward, heading));
+        projectile.direction = heading;
+        projectile.velocity = heading * projectile.speed;
+        transform.translation += projectile.velocity * delta_seconds;
+    }
+}
+
+/// Returns where the blast of a missile detonating at `position` strikes the target: the surface
+/// of its meshes facing the missile, so the [Armour](crate::damage::armour::Armour) facing struck
+/// is the side the missile came from. Targets without a mesh in the way, e.g. decoys, are struck
+/// at the missile position with a normal pointing from the target towards the missile.
+pub(crate) fn proximity_intersection(
+    ray_caster: &RayCaster<MyRaycastSet>,
+    position: Vec3,
+    target: Entity,
+    target_position: Vec3,
+) -> Intersection {
+    let offset = target_position - position;
+    let distance = offset.length();
+    let blast = Intersection::new(position, -offset.normalize_or_zero(), distance, None);
+    if distance <= f32::EPSILON {
+        return blast;
     }
+    let meshes = ray_caster.descendants(target);
+    ray_caster
+        .cast_all(&Ray3d::new(position, offset), &RayCastFilter::default())
+        .into_iter()
+        .find(|(entity, _)| meshes.contains(entity))
+        .map_or(blast, |(_, intersection)| intersection)
 }
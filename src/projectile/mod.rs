@@ -1,327 +1,734 @@
+pub mod countermeasure;
+pub mod event;
+pub mod guidance;
+mod input;
+pub mod label;
 mod missile;
+pub mod motor;
+pub mod pool;
 mod projectile;
+pub mod seeker;
 mod state;
 mod tag;
-
-use std::{
-    collections::BTreeMap,
-    sync::{Arc, Mutex},
-};
+pub mod weapon;
 
 use crate::{
-    camera::tag::CameraTag,
     projectile::tag::ProjectileDetectableTag,
     raycast::{
-        primitives::{Intersection, IntoUsize, Triangle},
+        packet::RayPacket4x2,
+        primitives::{Backfaces, Intersection},
         ray::Ray3d,
-        update_raycast::{compute_intersection, triangle_intersection},
-        RayCastMesh, RayCastSource,
+        RayCastFilter, RayCaster,
     },
     tag::{MyRaycastSet, PlayerModelTag},
+    FIRE_INPUT_SYSTEM,
 };
-use bevy::{
-    core::FloatOrd,
-    math::Vec3A,
-    prelude::*,
-    render::mesh::{Indices, VertexAttributeValues},
-    tasks::ComputeTaskPool,
-};
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::HashMap};
 
 use self::{
-    missile::{fire_missile, update_missile},
+    countermeasure::{
+        deploy_countermeasures, update_countermeasures, CountermeasureDefinition,
+        CountermeasureDefinitionLoader, CountermeasureDefinitions, CountermeasureRng,
+    },
+    event::{
+        DeployCountermeasureCommand, FireCommand, MissileSelfDestructEvent, ProjectileHitEvent,
+        ProjectileHitKind,
+    },
+    input::{keyboard_countermeasure_commands, keyboard_fire_commands},
+    label::ProjectileSystem,
+    missile::{insert_missile, update_missile},
+    pool::{PooledProjectile, ProjectilePool},
     projectile::{Bullet, Projectile},
     state::DefaultPluginState,
+    weapon::{
+        spread_direction, Hardpoint, ProjectileKind, WeaponDefinition, WeaponDefinitionLoader,
+        WeaponGroup, WeaponVisuals, Weapons,
+    },
 };
 
 #[derive(Default)]
 pub struct ProjectilePlugin;
 
-struct ProjectileTimer(Timer);
-struct MissileTimer(Timer);
-
 #[derive(Component, Default)]
 pub struct Target {
     pub velocity: Vec3,
 }
 
-const BULLET_SPEED: f32 = 200.0;
-const FIRE_RATE: f32 = 0.02;
-const MAX_BULLET_DISTANCE: f32 = 1000.0;
-pub(crate) const MAX_DISTANCE_SQUARED: f32 = MAX_BULLET_DISTANCE * MAX_BULLET_DISTANCE;
+const DEFAULT_CANNON: &str = "weapons/cannon.weapon.ron";
+const DEFAULT_MISSILE: &str = "weapons/missile.weapon.ron";
+const PROXIMITY_FUSE_DISTANCE: f32 = 0.05;
 
 impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<DefaultPluginState>()
-            .insert_resource(ProjectileTimer(Timer::from_seconds(FIRE_RATE, true)))
-            .insert_resource(MissileTimer(Timer::from_seconds(FIRE_RATE, true)))
-            .add_system(fire_bullet)
-            .add_system(fire_missile)
-            .add_system(update_bullet)
-            .add_system(update_missile)
-            .add_system(detect_hits);
+        if !app.world.contains_resource::<Events<ProjectileHitEvent>>() {
+            app.add_event::<ProjectileHitEvent>();
+        }
+        app.add_event::<FireCommand>()
+            .add_event::<MissileSelfDestructEvent>()
+            .add_event::<DeployCountermeasureCommand>()
+            .add_asset::<WeaponDefinition>()
+            .init_asset_loader::<WeaponDefinitionLoader>()
+            .add_asset::<CountermeasureDefinition>()
+            .init_asset_loader::<CountermeasureDefinitionLoader>()
+            .init_resource::<CountermeasureDefinitions>()
+            .init_resource::<DefaultPluginState>()
+            .init_resource::<CountermeasureRng>()
+            .init_resource::<ProjectilePool>()
+            .add_system(equip_default_weapons)
+            .add_system(keyboard_fire_commands.label(FIRE_INPUT_SYSTEM))
+            .add_system(keyboard_countermeasure_commands.label(FIRE_INPUT_SYSTEM))
+            // Spent projectiles are released to the pool right away but only hidden once commands
+            // are applied, so they are fired again after every system releasing them
+            .add_system(
+                fire_weapons
+                    .label(ProjectileSystem::Fire)
+                    .after(FIRE_INPUT_SYSTEM)
+                    .after(ProjectileSystem::DetectHits),
+            )
+            .add_system(
+                deploy_countermeasures
+                    .label(ProjectileSystem::Fire)
+                    .after(FIRE_INPUT_SYSTEM)
+                    .after(ProjectileSystem::DetectHits),
+            )
+            .add_system(update_countermeasures.label(ProjectileSystem::Move))
+            .add_system(update_bullet.label(ProjectileSystem::Move))
+            .add_system(update_missile.label(ProjectileSystem::Move))
+            .add_system(
+                detect_hits
+                    .label(ProjectileSystem::DetectHits)
+                    .after(ProjectileSystem::Move),
+            );
     }
 }
 
-fn fire_bullet(
+/// Gives the default cannon and missile launcher to player ships spawned without [Weapons].
+fn equip_default_weapons(
     mut commands: Commands,
-    camera_query: Query<&Transform, With<CameraTag>>,
-    player_query: Query<&GlobalTransform, With<PlayerModelTag>>,
-    keys: Res<Input<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, (Added<PlayerModelTag>, Without<Weapons>)>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).insert(
+            Weapons::default()
+                .with_hardpoint(Hardpoint::new(
+                    asset_server.load(DEFAULT_CANNON),
+                    Vec3::ZERO,
+                ))
+                .with_hardpoint(
+                    Hardpoint::new(asset_server.load(DEFAULT_MISSILE), Vec3::ZERO)
+                        .with_group(WeaponGroup::Secondary),
+                ),
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fire_weapons(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    definitions: Res<Assets<WeaponDefinition>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    time: Res<Time>,
-    mut timer: ResMut<ProjectileTimer>,
-    source_query: Query<&RayCastSource<MyRaycastSet>>,
-    target_query: Query<(&GlobalTransform, &Target), With<RayCastMesh<MyRaycastSet>>>,
+    mut visuals: Local<WeaponVisuals>,
+    mut pool: ResMut<ProjectilePool>,
+    mut fire_commands: EventReader<FireCommand>,
+    mut ship_query: Query<(&GlobalTransform, &mut Weapons)>,
 ) {
-    if keys.pressed(KeyCode::LControl) {
-        if timer.0.tick(time.delta()).just_finished() {
-            let camera_transform = camera_query.single();
-            let camera_global_transform = player_query.single();
-            let ray = Ray3d::from(camera_transform.compute_matrix());
-
-            let cube_handle = meshes.add(Mesh::from(shape::Cube::default()));
-            let cube_material_handle = materials.add(StandardMaterial {
-                base_color: Color::WHITE,
-                reflectance: 0.02,
-                unlit: true,
-                ..Default::default()
-            });
+    for (_, mut weapons) in ship_query.iter_mut() {
+        for hardpoint in weapons.hardpoints.iter_mut() {
+            hardpoint.cooldown = (hardpoint.cooldown - time.delta_seconds()).max(0.0);
+        }
+    }
 
-            let target = source_query
-                .single()
-                .intersections
-                .first()
-                .and_then(|(_, intersection)| Some(*intersection));
-
-            let dir: Vec3 = target
-                .and_then(|intersection| {
-                    let target_transform = Transform::from_translation(intersection.position);
-                    let d =
-                        camera_global_transform.looking_at(target_transform.translation, Vec3::Y);
-                    let d_ray = Ray3d::from(d.compute_matrix());
-                    Some(d_ray.direction.into())
-                })
-                .unwrap_or(ray.direction.into());
-
-            const BULLET_SPEED: f32 = 200.0;
-
-            commands
-                .spawn_bundle(PbrBundle {
-                    mesh: cube_handle.clone(),
-                    material: cube_material_handle.clone(),
-                    transform: Transform::from_translation(camera_global_transform.translation)
-                        .with_rotation(camera_transform.rotation)
-                        .with_scale(Vec3::new(0.1, 0.1, 0.8)),
+    let mut rng = rand::thread_rng();
+    for command in fire_commands.iter() {
+        let (ship_transform, mut weapons) = match ship_query.get_mut(command.shooter) {
+            Ok(ship) => ship,
+            Err(_) => continue,
+        };
+        let forward = ship_transform.rotation * -Vec3::Z;
+        let ship_up = ship_transform.rotation * Vec3::Y;
+        for hardpoint in weapons.hardpoints.iter_mut() {
+            if hardpoint.group != command.group || hardpoint.cooldown > 0.0 {
+                continue;
+            }
+            let definition = match definitions.get(&hardpoint.weapon) {
+                Some(definition) => definition,
+                None => continue,
+            };
+            hardpoint.cooldown = definition.fire_rate;
+
+            let (mesh, material) = visuals.get_or_create(
+                &hardpoint.weapon,
+                definition,
+                &asset_server,
+                &mut meshes,
+                &mut materials,
+            );
+            let muzzle = ship_transform.mul_vec3(hardpoint.offset);
+            let dir = match (definition.kind, command.aim_point) {
+                (ProjectileKind::Bullet, Some(aim_point)) => {
+                    (aim_point - muzzle).normalize_or_zero()
+                }
+                _ => command.aim.direction(),
+            };
+            // An aim point right on the muzzle gives no direction, so fire straight ahead
+            let dir = if dir == Vec3::ZERO { forward } else { dir };
+            let dir = spread_direction(dir, definition.spread, &mut rng);
+            // Firing straight up or down needs another up vector to orient the projectile
+            let up = if dir.cross(ship_up).length_squared() > 1e-6 {
+                ship_up
+            } else {
+                ship_transform.rotation * Vec3::Z
+            };
+            let transform = Transform::from_translation(muzzle)
+                .looking_at(muzzle + dir, up)
+                .with_scale(Vec3::from(definition.visual.scale));
+            let ray = Ray3d::new(muzzle, dir);
+
+            let mut projectile = pool.spawn(
+                &mut commands,
+                &hardpoint.weapon,
+                PbrBundle {
+                    mesh,
+                    material,
+                    transform,
                     ..Default::default()
-                })
-                .insert(Name::new("Bullet"))
-                .insert(Bullet)
-                .insert(Projectile {
-                    ballistic: false,
-                    direction: dir,
-                    velocity: dir * BULLET_SPEED,
-                    ray,
-                    speed: BULLET_SPEED,
-                })
-                .insert(ProjectileDetectableTag);
+                },
+            );
+            match definition.kind {
+                ProjectileKind::Bullet => {
+                    insert_bullet(&mut projectile, definition, transform, ray, command.shooter);
+                }
+                ProjectileKind::Missile => {
+                    insert_missile(
+                        &mut projectile,
+                        definition,
+                        transform,
+                        ray,
+                        command.shooter,
+                        command.target,
+                    );
+                }
+            }
         }
     }
 }
 
+fn insert_bullet(
+    projectile: &mut EntityCommands,
+    definition: &WeaponDefinition,
+    transform: Transform,
+    ray: Ray3d,
+    shooter: Entity,
+) {
+    let dir = ray.direction();
+    projectile
+        .insert(Name::new(definition.name.clone()))
+        .insert(Bullet)
+        .insert(Projectile {
+            ballistic: false,
+            direction: dir,
+            velocity: dir * definition.speed,
+            ray,
+            speed: definition.speed,
+            damage: definition.damage,
+            shooter,
+            origin: transform.translation,
+            previous_translation: transform.translation,
+            range: definition.range,
+        })
+        .insert(ProjectileDetectableTag);
+}
+
 fn update_bullet(
     mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(&mut Transform, &Projectile, Entity), With<Bullet>>,
-    player_query: Query<&GlobalTransform, With<PlayerModelTag>>,
+    mut pool: ResMut<ProjectilePool>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut Projectile,
+            Option<&PooledProjectile>,
+            Entity,
+        ),
+        With<Bullet>,
+    >,
 ) {
-    let player_global_translation = player_query.single().translation;
-    for (mut transform, projectile, entity) in query.iter_mut() {
-        if (transform.translation - player_global_translation).length_squared()
-            > MAX_DISTANCE_SQUARED
-        {
-            commands.entity(entity).despawn();
+    for (mut transform, mut projectile, pooled, entity) in query.iter_mut() {
+        if projectile.is_out_of_range(transform.translation) {
+            pool.release(&mut commands, entity, pooled);
         } else {
-            transform.translation += BULLET_SPEED * projectile.direction * time.delta_seconds();
+            projectile.advance(&mut transform, time.delta_seconds());
         }
     }
 }
 
+/// Sweeps every projectile along the segment it travelled since the previous tick, so fast
+/// projectiles cannot tunnel through thin meshes whatever the frame time, and reports only its
+/// first hit before releasing it.
 fn detect_hits(
     mut commands: Commands,
-    task_pool: Res<ComputeTaskPool>,
-    meshes: Res<Assets<Mesh>>,
-    culling_query: Query<
-        (
-            &Visibility,
-            Option<&bevy::render::primitives::Aabb>,
-            &GlobalTransform,
-            Entity,
-        ),
-        With<RayCastMesh<MyRaycastSet>>,
-    >,
-    mesh_query: Query<
-        (&Handle<Mesh>, &Name, &GlobalTransform, Entity),
-        With<RayCastMesh<MyRaycastSet>>,
-    >,
+    mut hit_events: EventWriter<ProjectileHitEvent>,
+    mut pool: ResMut<ProjectilePool>,
+    ray_caster: RayCaster<MyRaycastSet>,
     projectiles_query: Query<
-        (&Transform, &GlobalTransform, &Projectile, Entity),
+        (&Transform, &Projectile, Option<&PooledProjectile>, Entity),
         With<ProjectileDetectableTag>,
     >,
 ) {
-    for (transform, projectile_global_transform, projectile, entity) in projectiles_query.iter() {
-        let ray = Ray3d::from(transform.compute_matrix());
-        let culled_entities: Vec<_> = culling_query
+    // Projectiles are fired from inside their ship and never hit it, so the projectiles of each
+    // shooter are swept together with its meshes excluded
+    let mut shooters: HashMap<Entity, Vec<_>> = HashMap::default();
+    for projectile in projectiles_query.iter() {
+        shooters
+            .entry(projectile.1.shooter)
+            .or_default()
+            .push(projectile);
+    }
+
+    for (shooter, projectiles) in shooters {
+        let exclude = ray_caster.descendants(shooter);
+        let filter = RayCastFilter::default().with_exclude(&exclude);
+        let segments: Vec<(Vec3, Vec3)> = projectiles
             .iter()
-            .filter_map(|(visibility, aab, transform, entity)| {
-                if visibility.is_visible {
-                    aab.and_then(|x| ray.intersects_aabb(x, &transform.compute_matrix()))
-                        .and_then(|[_, far]| if far >= 0.0 { Some(entity) } else { None })
-                } else {
-                    return None;
-                }
+            .map(|(transform, projectile, _, _)| {
+                (projectile.previous_translation, transform.translation)
             })
             .collect();
-        if !culled_entities.is_empty() {
-            let picks = Arc::new(Mutex::new(BTreeMap::new()));
-            mesh_query.par_for_each(
-                &task_pool,
-                32,
-                |(mesh_handle, name, mesh_global_transform, entity)| {
-                    if culled_entities.contains(&entity) {
-                        let intersection = meshes.get(mesh_handle).and_then(|x| {
-                            compute_bullet_intersection(
-                                x,
-                                &mesh_global_transform.compute_matrix(),
-                                projectile_global_transform,
-                                &projectile,
-                            )
-                        });
-                        match intersection {
-                            Some(intersection) => {
-                                picks
-                                    .lock()
-                                    .unwrap()
-                                    .insert(FloatOrd(intersection.distance()), name.as_str());
-                            }
-                            None => {
-                                let distance = (mesh_global_transform.translation
-                                    - projectile_global_transform.translation)
-                                    .length();
-                                if projectile.ballistic && distance < 0.05 {
-                                    picks
-                                        .lock()
-                                        .unwrap()
-                                        .insert(FloatOrd(distance), name.as_str());
-                                }
-                            }
-                        }
-                    }
-                },
-            );
-            let picks: Vec<_> = Arc::try_unwrap(picks)
-                .unwrap()
-                .into_inner()
-                .unwrap()
-                .into_values()
-                .collect();
-            if !picks.is_empty() {
+        let impacts = sweep_segments(&ray_caster, &segments, &filter);
+
+        for ((transform, projectile, pooled, entity), impact) in
+            projectiles.into_iter().zip(impacts)
+        {
+            let impact = impact
+                .map(|(target, intersection)| (target, intersection, ProjectileHitKind::Impact));
+            let hit = impact.or_else(|| {
                 if projectile.ballistic {
-                    println!("BOOM {:?}", picks);
-                    commands.entity(entity).despawn();
+                    proximity_hit(
+                        &ray_caster,
+                        projectile.previous_translation,
+                        transform.translation,
+                        projectile.direction,
+                        &filter,
+                    )
+                    .map(|(target, intersection)| {
+                        (target, intersection, ProjectileHitKind::Proximity)
+                    })
                 } else {
-                    println!("HIT! {:?}", picks);
+                    None
                 }
+            });
+
+            if let Some((target, intersection, kind)) = hit {
+                hit_events.send(ProjectileHitEvent {
+                    projectile: entity,
+                    generation: pooled.map_or(0, |pooled| pooled.generation),
+                    target,
+                    intersection,
+                    velocity: projectile.velocity,
+                    damage: projectile.damage,
+                    kind,
+                });
+                // Stop at the first thing struck so it is only damaged once
+                pool.release(&mut commands, entity, pooled);
             }
-        } else {
-            // commands.entity(entity).remove::<ProjectileDetectableTag>();
         }
     }
 }
 
-fn compute_bullet_intersection(
-    mesh: &Mesh,
-    mesh_to_world: &Mat4,
-    projectile_global_transform: &GlobalTransform,
-    projectile: &Projectile,
-) -> Option<Intersection> {
-    if let (
-        Some(VertexAttributeValues::Float32x3(positions)),
-        Some(VertexAttributeValues::Float32x3(normals)),
-        Some(Indices::U32(indices)),
-    ) = (
-        mesh.attribute(Mesh::ATTRIBUTE_POSITION),
-        mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
-        mesh.indices(),
-    ) {
-        compute_bullet_intersection_per_triangle(
-            mesh_to_world,
-            projectile_global_transform,
-            positions,
-            normals,
-            indices,
-            &projectile.ray,
-        )
-    } else {
-        None
+/// Returns the first mesh passing the filter crossed by each segment, casting all of them
+/// together in ray packets.
+pub fn sweep_segments(
+    ray_caster: &RayCaster<MyRaycastSet>,
+    segments: &[(Vec3, Vec3)],
+    filter: &RayCastFilter,
+) -> Vec<Option<(Entity, Intersection)>> {
+    let mut impacts = vec![None; segments.len()];
+    // A segment of no length cannot cross anything, nor be cast as a ray
+    let moving: Vec<(usize, Ray3d, f32)> = segments
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &(from, to))| {
+            let offset = to - from;
+            let length = offset.length();
+            (length > f32::EPSILON).then(|| (index, Ray3d::new(from, offset), length))
+        })
+        .collect();
+    if moving.is_empty() {
+        return impacts;
     }
+
+    let rays: Vec<Ray3d> = moving.iter().map(|&(_, ray, _)| ray).collect();
+    let max_length = moving
+        .iter()
+        .map(|&(_, _, length)| length)
+        .fold(0.0, f32::max);
+    let hits = ray_caster.cast_packet::<RayPacket4x2>(&rays, &filter.with_max_distance(max_length));
+    // Every ray is cast as far as the longest segment, so drop hits past the end of its own
+    for ((index, _, length), hit) in moving.into_iter().zip(hits) {
+        impacts[index] = hit.filter(|(_, intersection)| intersection.distance() <= length);
+    }
+    impacts
+}
+
+/// Sweeps the proximity fuse, a sphere around the projectile, along the segment it travelled and
+/// returns the first mesh passing the filter it came close to. Surfaces set the fuse off whichever
+/// way they face, so backfaces are included.
+fn proximity_hit(
+    ray_caster: &RayCaster<MyRaycastSet>,
+    from: Vec3,
+    to: Vec3,
+    direction: Vec3,
+    filter: &RayCastFilter,
+) -> Option<(Entity, Intersection)> {
+    let offset = to - from;
+    let length = offset.length();
+    // A projectile that did not move still checks what is around it
+    let direction = if length > f32::EPSILON {
+        offset
+    } else {
+        direction
+    };
+    ray_caster.sphere_cast(
+        &Ray3d::new(from, direction),
+        PROXIMITY_FUSE_DISTANCE,
+        &filter
+            .with_max_distance(length)
+            .with_backfaces(Backfaces::Include),
+    )
 }
 
-fn compute_bullet_intersection_per_triangle(
-    mesh_to_world: &Mat4,
-    projectile_global_transform: &GlobalTransform,
-    positions: &Vec<[f32; 3]>,
-    normals: &Vec<[f32; 3]>,
-    indices: &Vec<u32>,
-    ray: &Ray3d,
-) -> Option<Intersection> {
-    let mut min_pick_distance = 1.0;
-    let mut pick_intersection = None;
-    let world_to_mesh = mesh_to_world.inverse();
-
-    let projectile_global_translation = projectile_global_transform.translation;
-
-    let mesh_space_ray = Ray3d::new(
-        world_to_mesh.transform_point3(projectile_global_translation),
-        world_to_mesh.transform_vector3(ray.direction.into()),
-    );
-
-    for index in indices.chunks(3) {
-        let tri_positions = [
-            Vec3A::from(positions[index[0].into_usize()]),
-            Vec3A::from(positions[index[1].into_usize()]),
-            Vec3A::from(positions[index[2].into_usize()]),
-        ];
-
-        let tri_normals = [
-            Vec3A::from(normals[index[0].into_usize()]),
-            Vec3A::from(normals[index[1].into_usize()]),
-            Vec3A::from(normals[index[2].into_usize()]),
-        ];
-
-        if let Some(i) = triangle_intersection(
-            tri_positions,
-            Some(tri_normals),
-            min_pick_distance,
-            mesh_space_ray,
-        ) {
-            pick_intersection = Some(Intersection::new(
-                mesh_to_world.transform_point3(i.position),
-                mesh_to_world.transform_vector3(i.normal),
-                mesh_to_world
-                    .transform_vector3(mesh_space_ray.direction() * i.distance)
-                    .length(),
-                i.triangle.map(|tri| {
-                    Triangle::from([
-                        mesh_to_world.transform_point3a(tri.v0),
-                        mesh_to_world.transform_point3a(tri.v1),
-                        mesh_to_world.transform_point3a(tri.v2),
-                    ])
-                }),
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::AssetPlugin,
+        render::primitives::Aabb,
+        tasks::{ComputeTaskPool, IoTaskPool, TaskPool},
+    };
+
+    use super::{missile::proximity_intersection, *};
+    use crate::{
+        damage::health::Damage,
+        projectile::weapon::ProjectileVisual,
+        raycast::{deform::DeformedMeshes, state::DefaultPluginState, RayCastMesh},
+    };
+
+    const SPEED: f32 = 1000.0;
+    const TARGET_DISTANCE: f32 = 50.0;
+
+    fn spawn_quad(app: &mut App, z: f32) -> Entity {
+        // A plane facing +Z, back towards the muzzle at the origin
+        let mesh = Mesh::from(shape::Plane { size: 20.0 });
+        let aabb = mesh.compute_aabb().unwrap();
+        let mesh = app
+            .world
+            .get_resource_mut::<Assets<Mesh>>()
+            .unwrap()
+            .add(mesh);
+        let transform = Transform::from_xyz(0.0, 0.0, z)
+            .with_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_2));
+        app.world
+            .spawn()
+            .insert(mesh)
+            .insert(aabb)
+            .insert(transform)
+            .insert(GlobalTransform::from(transform))
+            .insert(Visibility::default())
+            .insert(RayCastMesh::<MyRaycastSet>::default())
+            .id()
+    }
+
+    /// Fires a bullet from inside a ship at a thin target, moving it with the given time step,
+    /// and returns the entities it hit.
+    fn hits(delta_seconds: f32) -> (Vec<Entity>, Entity) {
+        let mut app = App::new();
+        app.insert_resource(IoTaskPool(TaskPool::new()))
+            .insert_resource(ComputeTaskPool(TaskPool::new()))
+            .add_plugin(AssetPlugin)
+            .add_asset::<Mesh>()
+            .init_resource::<DefaultPluginState<MyRaycastSet>>()
+            .init_resource::<DeformedMeshes>()
+            .init_resource::<ProjectilePool>()
+            .add_event::<ProjectileHitEvent>()
+            .add_system(detect_hits);
+
+        // The hull of the ship is in front of the muzzle
+        let hull = spawn_quad(&mut app, -1.0);
+        let ship = app.world.spawn().push_children(&[hull]).id();
+        let target = spawn_quad(&mut app, -TARGET_DISTANCE);
+
+        let direction = -Vec3::Z;
+        let bullet = app
+            .world
+            .spawn()
+            .insert(Transform::identity())
+            .insert(Projectile {
+                ballistic: false,
+                velocity: direction * SPEED,
+                direction,
+                ray: Ray3d::new(Vec3::ZERO, direction),
+                speed: SPEED,
+                damage: Damage::default(),
+                shooter: ship,
+                origin: Vec3::ZERO,
+                previous_translation: Vec3::ZERO,
+                range: 4.0 * TARGET_DISTANCE,
+            })
+            .insert(ProjectileDetectableTag)
+            .id();
+
+        let mut hit_reader = app
+            .world
+            .get_resource::<Events<ProjectileHitEvent>>()
+            .unwrap()
+            .get_reader();
+        let mut hits = Vec::new();
+        let ticks = (2.0 * TARGET_DISTANCE / (SPEED * delta_seconds)).ceil() as usize;
+        for _ in 0..ticks {
+            if let Some(mut entity) = app.world.get_entity_mut(bullet) {
+                let mut transform = *entity.get::<Transform>().unwrap();
+                entity
+                    .get_mut::<Projectile>()
+                    .unwrap()
+                    .advance(&mut transform, delta_seconds);
+                entity.insert(transform);
+            }
+            app.update();
+            let hit_events = app
+                .world
+                .get_resource::<Events<ProjectileHitEvent>>()
+                .unwrap();
+            hits.extend(hit_reader.iter(hit_events).map(|event| event.target));
+        }
+        (hits, target)
+    }
+
+    #[test]
+    fn small_time_step_hits_once() {
+        let (hits, target) = hits(1.0 / 240.0);
+        assert_eq!(hits, vec![target]);
+    }
+
+    #[test]
+    fn large_time_step_does_not_tunnel() {
+        // The bullet moves twice the distance to the target in a single step
+        let (hits, target) = hits(1.0 / 10.0);
+        assert_eq!(hits, vec![target]);
+    }
+
+    #[test]
+    fn missile_blast_strikes_surface_facing_it() {
+        let mut app = App::new();
+        app.insert_resource(IoTaskPool(TaskPool::new()))
+            .insert_resource(ComputeTaskPool(TaskPool::new()))
+            .add_plugin(AssetPlugin)
+            .add_asset::<Mesh>()
+            .init_resource::<DefaultPluginState<MyRaycastSet>>()
+            .init_resource::<DeformedMeshes>()
+            .init_resource::<Vec<Intersection>>();
+        let target = spawn_quad(&mut app, -TARGET_DISTANCE);
+        let decoy = app
+            .world
+            .spawn()
+            .insert(GlobalTransform::from_translation(
+                -Vec3::Z * TARGET_DISTANCE,
+            ))
+            .id();
+
+        // Off to the side of the centre of both, in front of the quad
+        let position = Vec3::new(4.0, 0.0, 3.0 - TARGET_DISTANCE);
+        app.add_system(
+            move |ray_caster: RayCaster<MyRaycastSet>,
+                  mut intersections: ResMut<Vec<Intersection>>| {
+                for target in [target, decoy] {
+                    let target_position = ray_caster.transform(target).unwrap().translation;
+                    intersections.push(proximity_intersection(
+                        &ray_caster,
+                        position,
+                        target,
+                        target_position,
+                    ));
+                }
+            },
+        );
+        app.update();
+
+        let intersections = app.world.get_resource::<Vec<Intersection>>().unwrap();
+        // The quad is struck on its face, whatever the direction to its centre
+        assert!(intersections[0].normal.abs_diff_eq(Vec3::Z, 1e-4));
+        assert!((intersections[0].position.z + TARGET_DISTANCE).abs() < 1e-4);
+        // Without a mesh the blast comes from the missile
+        assert!(intersections[1]
+            .normal
+            .abs_diff_eq(Vec3::new(0.8, 0.0, 0.6), 1e-4));
+        assert_eq!(intersections[1].position, position);
+    }
+
+    /// Spawns a ship at the origin with a cannon that can fire on every tick.
+    fn spawn_ship(app: &mut App) -> Entity {
+        let weapon = app
+            .world
+            .get_resource_mut::<Assets<WeaponDefinition>>()
+            .unwrap()
+            .add(WeaponDefinition {
+                name: "Test cannon".to_string(),
+                kind: ProjectileKind::Bullet,
+                speed: SPEED,
+                fire_rate: 0.0,
+                spread: 0.0,
+                range: 1000.0,
+                damage: Damage::default(),
+                visual: ProjectileVisual {
+                    mesh: None,
+                    scale: [1.0; 3],
+                    color: [1.0; 4],
+                    unlit: true,
+                    reflectance: 0.0,
+                },
+                guidance: None,
+                motor: Default::default(),
+            });
+        app.world
+            .spawn()
+            .insert(GlobalTransform::identity())
+            .insert(Weapons::default().with_hardpoint(Hardpoint::new(weapon, Vec3::ZERO)))
+            .id()
+    }
+
+    fn fire(app: &mut App, ship: Entity) {
+        app.world
+            .get_resource_mut::<Events<FireCommand>>()
+            .unwrap()
+            .send(FireCommand {
+                shooter: ship,
+                group: WeaponGroup::Primary,
+                aim: Ray3d::new(Vec3::ZERO, -Vec3::Z),
+                aim_point: None,
+                target: None,
+            });
+    }
+
+    /// Stands in for the projectiles hitting something or running out of range right away.
+    fn release_projectiles(
+        mut commands: Commands,
+        mut pool: ResMut<ProjectilePool>,
+        query: Query<(Option<&PooledProjectile>, Entity), With<ProjectileDetectableTag>>,
+    ) {
+        for (pooled, entity) in query.iter() {
+            pool.release(&mut commands, entity, pooled);
+        }
+    }
+
+    #[test]
+    fn firing_reuses_projectiles_and_assets() {
+        let mut app = App::new();
+        app.insert_resource(IoTaskPool(TaskPool::new()))
+            .add_plugin(AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_asset::<WeaponDefinition>()
+            .init_resource::<Time>()
+            .init_resource::<ProjectilePool>()
+            .add_event::<FireCommand>()
+            .add_system(fire_weapons.label(ProjectileSystem::Fire))
+            .add_system(release_projectiles.after(ProjectileSystem::Fire));
+
+        // Fires on every tick, as time does not advance without the time system
+        let ship = spawn_ship(&mut app);
+
+        let mut counts = Vec::new();
+        for _ in 0..100 {
+            fire(&mut app, ship);
+            app.update();
+            counts.push((
+                app.world.get_resource::<Assets<Mesh>>().unwrap().len(),
+                app.world
+                    .get_resource::<Assets<StandardMaterial>>()
+                    .unwrap()
+                    .len(),
+                app.world.entities().len(),
             ));
-            min_pick_distance = i.distance();
         }
+        // Two projectiles take turns, one fired while the other waits in the pool
+        assert_eq!(counts[1], (1, 1, 3));
+        assert!(
+            counts.iter().skip(1).all(|&count| count == counts[1]),
+            "{:?}",
+            counts
+        );
     }
 
-    pick_intersection
+    #[test]
+    fn fire_reuses_projectile_released_in_same_tick() {
+        let mut app = App::new();
+        app.insert_resource(IoTaskPool(TaskPool::new()))
+            .insert_resource(ComputeTaskPool(TaskPool::new()))
+            .add_plugin(AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .add_asset::<WeaponDefinition>()
+            .init_resource::<Time>()
+            .init_resource::<DefaultPluginState<MyRaycastSet>>()
+            .init_resource::<DeformedMeshes>()
+            .init_resource::<ProjectilePool>()
+            .add_event::<FireCommand>()
+            .add_event::<ProjectileHitEvent>()
+            .add_system(detect_hits.label(ProjectileSystem::DetectHits))
+            .add_system(
+                fire_weapons
+                    .label(ProjectileSystem::Fire)
+                    .after(ProjectileSystem::DetectHits),
+            );
+        let ship = spawn_ship(&mut app);
+        spawn_quad(&mut app, -TARGET_DISTANCE);
+
+        fire(&mut app, ship);
+        app.update();
+        let shots: Vec<_> = app
+            .world
+            .query_filtered::<Entity, With<Projectile>>()
+            .iter(&app.world)
+            .collect();
+        assert_eq!(shots.len(), 1);
+        let shot = shots[0];
+
+        // Move the shot through the target, so it is released on the same tick the next is fired
+        let mut entity = app.world.entity_mut(shot);
+        let mut transform = *entity.get::<Transform>().unwrap();
+        entity
+            .get_mut::<Projectile>()
+            .unwrap()
+            .advance(&mut transform, 2.0 * TARGET_DISTANCE / SPEED);
+        entity.insert(transform);
+        fire(&mut app, ship);
+        app.update();
+
+        let hit_events = app
+            .world
+            .get_resource::<Events<ProjectileHitEvent>>()
+            .unwrap();
+        let hits: Vec<_> = hit_events
+            .get_reader()
+            .iter(hit_events)
+            .map(|event| (event.projectile, event.generation))
+            .collect();
+        assert_eq!(hits, vec![(shot, 0)]);
+        // The next shot took over the entity and kept what it was fired with, and its generation
+        // tells it apart from the shot that hit
+        let entity = app.world.entity(shot);
+        assert!(entity.get::<Projectile>().is_some());
+        assert!(entity.get::<ProjectileDetectableTag>().is_some());
+        assert!(entity.get::<Visibility>().unwrap().is_visible);
+        assert_eq!(entity.get::<PooledProjectile>().unwrap().generation, 1);
+        assert_eq!(
+            app.world
+                .get_resource::<ProjectilePool>()
+                .unwrap()
+                .free_count(),
+            0
+        );
+    }
 }
@@ -0,0 +1,291 @@
+use serde::Deserialize;
+
+/// Stage of the flight of a missile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlightPhase {
+    /// Drifting away from the launcher before the motor ignites, unguided.
+    Launch,
+    /// Burning at full thrust.
+    Boost,
+    /// Burning the remaining fuel at cruise thrust.
+    Sustain,
+    /// Out of fuel and slowing down.
+    Coast,
+    /// Close enough to the target to commit to the intercept.
+    Terminal,
+}
+
+impl Default for FlightPhase {
+    fn default() -> Self {
+        FlightPhase::Launch
+    }
+}
+
+/// Why a missile blew itself up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfDestructReason {
+    /// The target was lost for longer than the lock loss timeout.
+    LostLock,
+    /// The missile ran out of fuel and slowed below its minimum speed.
+    OutOfFuel,
+    /// The missile flew for longer than its lifetime.
+    Expired,
+}
+
+/// Motor and flight limits of a missile.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct MotorParams {
+    /// Seconds after launch before the motor ignites.
+    #[serde(default)]
+    pub launch_drift: f32,
+    /// Seconds the boost burns for.
+    #[serde(default)]
+    pub boost_time: f32,
+    /// Speed gained per second during the boost.
+    #[serde(default)]
+    pub boost_acceleration: f32,
+    /// Speed gained per second while sustaining.
+    #[serde(default)]
+    pub sustain_acceleration: f32,
+    /// Seconds of sustain burn after the boost.
+    #[serde(default)]
+    pub fuel: f32,
+    /// Fraction of the speed lost per second while coasting.
+    #[serde(default)]
+    pub drag: f32,
+    /// Coasting below this speed sets off the self-destruct.
+    #[serde(default)]
+    pub min_speed: f32,
+    /// Distance to the target at which the missile enters its terminal phase.
+    #[serde(default)]
+    pub terminal_range: f32,
+    /// Seconds before the missile blows itself up whatever happens.
+    pub max_lifetime: f32,
+    /// Seconds without a target before the missile blows itself up.
+    pub lock_loss_timeout: f32,
+}
+
+impl Default for MotorParams {
+    fn default() -> Self {
+        MotorParams {
+            launch_drift: 0.0,
+            boost_time: 0.0,
+            boost_acceleration: 0.0,
+            sustain_acceleration: 0.0,
+            fuel: 0.0,
+            drag: 0.0,
+            min_speed: 0.0,
+            terminal_range: 0.0,
+            max_lifetime: 10.0,
+            lock_loss_timeout: 1.0,
+        }
+    }
+}
+
+/// Flight state of a missile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Motor {
+    pub params: MotorParams,
+    pub phase: FlightPhase,
+    /// Seconds since launch.
+    pub age: f32,
+    /// Seconds of sustain burn left.
+    pub fuel: f32,
+    /// Seconds since the target was lost, if it was.
+    pub lost_lock: Option<f32>,
+}
+
+impl Motor {
+    pub fn new(params: MotorParams) -> Self {
+        Motor {
+            params,
+            phase: FlightPhase::default(),
+            age: 0.0,
+            fuel: params.fuel,
+            lost_lock: None,
+        }
+    }
+
+    /// Whether the guidance steers the missile in the current phase.
+    pub fn is_guided(&self) -> bool {
+        self.phase != FlightPhase::Launch
+    }
+
+    /// Advances the flight by one tick and returns the new speed, or why the missile must blow
+    /// itself up. `target_distance` is `None` while the missile has no target.
+    pub fn update(
+        &mut self,
+        speed: f32,
+        target_distance: Option<f32>,
+        delta_seconds: f32,
+    ) -> Result<f32, SelfDestructReason> {
+        let params = self.params;
+        self.age += delta_seconds;
+        if self.age > params.max_lifetime {
+            return Err(SelfDestructReason::Expired);
+        }
+
+        self.lost_lock = match (target_distance, self.lost_lock) {
+            (Some(_), _) => None,
+            (None, Some(lost)) => Some(lost + delta_seconds),
+            (None, None) => None,
+        };
+        if self.lost_lock.unwrap_or(0.0) > params.lock_loss_timeout {
+            return Err(SelfDestructReason::LostLock);
+        }
+
+        let burn_time = self.age - params.launch_drift;
+        self.phase = if burn_time < 0.0 {
+            FlightPhase::Launch
+        } else if burn_time < params.boost_time {
+            FlightPhase::Boost
+        } else if target_distance.map_or(false, |distance| distance <= params.terminal_range) {
+            FlightPhase::Terminal
+        } else if self.fuel > 0.0 {
+            FlightPhase::Sustain
+        } else {
+            FlightPhase::Coast
+        };
+
+        match self.phase {
+            FlightPhase::Launch => Ok(speed),
+            FlightPhase::Boost => Ok(speed + params.boost_acceleration * delta_seconds),
+            FlightPhase::Sustain | FlightPhase::Terminal if self.fuel > 0.0 => {
+                self.fuel = (self.fuel - delta_seconds).max(0.0);
+                Ok(speed + params.sustain_acceleration * delta_seconds)
+            }
+            // Committed to the intercept, coasting the rest of the way
+            FlightPhase::Terminal => Ok(speed * (1.0 - params.drag * delta_seconds).max(0.0)),
+            FlightPhase::Sustain | FlightPhase::Coast => {
+                let speed = speed * (1.0 - params.drag * delta_seconds).max(0.0);
+                if speed < params.min_speed {
+                    Err(SelfDestructReason::OutOfFuel)
+                } else {
+                    Ok(speed)
+                }
+            }
+        }
+    }
+
+    /// Marks the target as lost, starting the lock loss timeout.
+    pub fn lose_lock(&mut self) {
+        if self.lost_lock.is_none() {
+            self.lost_lock = Some(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELTA_SECONDS: f32 = 0.1;
+    const FAR: Option<f32> = Some(1000.0);
+
+    fn params() -> MotorParams {
+        MotorParams {
+            launch_drift: 0.25,
+            boost_time: 0.5,
+            boost_acceleration: 100.0,
+            sustain_acceleration: 10.0,
+            fuel: 0.5,
+            drag: 0.5,
+            min_speed: 1.0,
+            terminal_range: 50.0,
+            max_lifetime: 100.0,
+            lock_loss_timeout: 1.0,
+        }
+    }
+
+    #[test]
+    fn phases_follow_the_burn() {
+        let mut motor = Motor::new(params());
+        let mut speed = 20.0;
+        let mut phases = Vec::new();
+        for _ in 0..20 {
+            let previous_speed = speed;
+            speed = motor.update(speed, FAR, DELTA_SECONDS).unwrap();
+            match motor.phase {
+                FlightPhase::Launch => assert_eq!(speed, previous_speed),
+                FlightPhase::Boost | FlightPhase::Sustain => assert!(speed > previous_speed),
+                FlightPhase::Coast => assert!(speed < previous_speed),
+                FlightPhase::Terminal => unreachable!(),
+            }
+            if phases.last() != Some(&motor.phase) {
+                phases.push(motor.phase);
+            }
+        }
+        assert_eq!(
+            phases,
+            vec![
+                FlightPhase::Launch,
+                FlightPhase::Boost,
+                FlightPhase::Sustain,
+                FlightPhase::Coast
+            ]
+        );
+        assert!(!Motor::new(params()).is_guided());
+        assert!(motor.is_guided());
+    }
+
+    #[test]
+    fn terminal_within_range() {
+        let mut motor = Motor::new(params());
+        for _ in 0..10 {
+            motor.update(20.0, FAR, DELTA_SECONDS).unwrap();
+        }
+        motor.update(20.0, Some(40.0), DELTA_SECONDS).unwrap();
+        assert_eq!(motor.phase, FlightPhase::Terminal);
+    }
+
+    #[test]
+    fn lost_lock_self_destructs_after_timeout() {
+        // Quarter seconds add up to the timeout exactly
+        let mut motor = Motor::new(params());
+        motor.lose_lock();
+        for _ in 0..4 {
+            assert!(motor.update(20.0, None, 0.25).is_ok());
+        }
+        assert_eq!(
+            motor.update(20.0, None, 0.25),
+            Err(SelfDestructReason::LostLock)
+        );
+
+        // Reacquiring the target within the timeout restores the lock
+        let mut motor = Motor::new(params());
+        motor.lose_lock();
+        for _ in 0..5 {
+            motor.update(20.0, None, DELTA_SECONDS).unwrap();
+        }
+        motor.update(20.0, FAR, DELTA_SECONDS).unwrap();
+        assert_eq!(motor.lost_lock, None);
+    }
+
+    #[test]
+    fn out_of_fuel_self_destructs_below_min_speed() {
+        let mut motor = Motor::new(MotorParams {
+            drag: 0.5,
+            min_speed: 5.0,
+            ..Default::default()
+        });
+        assert_eq!(motor.update(10.0, FAR, 1.0), Ok(5.0));
+        assert_eq!(motor.phase, FlightPhase::Coast);
+        assert_eq!(
+            motor.update(5.0, FAR, 1.0),
+            Err(SelfDestructReason::OutOfFuel)
+        );
+    }
+
+    #[test]
+    fn expires_after_lifetime() {
+        let mut motor = Motor::new(MotorParams {
+            max_lifetime: 1.0,
+            ..Default::default()
+        });
+        assert!(motor.update(10.0, FAR, 0.6).is_ok());
+        assert_eq!(
+            motor.update(10.0, FAR, 0.6),
+            Err(SelfDestructReason::Expired)
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::HashMap};
+
+use super::{
+    missile::Missile,
+    projectile::{Bullet, Projectile},
+    tag::ProjectileDetectableTag,
+    weapon::WeaponDefinition,
+};
+
+/// Weapon a pooled projectile was fired by, which it goes back to the pool of once spent.
+#[derive(Component, Debug, Clone)]
+pub struct PooledProjectile {
+    pub weapon: Handle<WeaponDefinition>,
+    /// Number of times the entity was fired before. Pooled entities keep their id from shot to
+    /// shot, so events naming a projectile, e.g.
+    /// [ProjectileHitEvent](super::event::ProjectileHitEvent), carry its generation to tell a later
+    /// shot apart.
+    pub generation: u32,
+}
+
+/// Spent projectile entities, hidden and kept per weapon to be fired again instead of spawning
+/// new ones, with the generation they are fired again with.
+#[derive(Default)]
+pub struct ProjectilePool {
+    free: HashMap<Handle<WeaponDefinition>, Vec<(Entity, u32)>>,
+}
+
+impl ProjectilePool {
+    /// Reuses a spent projectile of the weapon if there is one, or spawns a new entity.
+    pub fn spawn<'w, 's, 'a>(
+        &mut self,
+        commands: &'a mut Commands<'w, 's>,
+        weapon: &Handle<WeaponDefinition>,
+        bundle: PbrBundle,
+    ) -> EntityCommands<'w, 's, 'a> {
+        let (mut entity_commands, generation) = match self.free.get_mut(weapon).and_then(Vec::pop) {
+            Some((entity, generation)) => {
+                let mut entity_commands = commands.entity(entity);
+                entity_commands.insert_bundle(bundle);
+                (entity_commands, generation)
+            }
+            None => (commands.spawn_bundle(bundle), 0),
+        };
+        entity_commands.insert(PooledProjectile {
+            weapon: weapon.clone(),
+            generation,
+        });
+        entity_commands
+    }
+
+    /// Hides a spent projectile and keeps it for its weapon, or despawns it if it was not fired
+    /// from one. The entity id is reused by the next shot, see [PooledProjectile::generation].
+    pub fn release(
+        &mut self,
+        commands: &mut Commands,
+        entity: Entity,
+        pooled: Option<&PooledProjectile>,
+    ) {
+        let pooled = match pooled {
+            Some(pooled) => pooled,
+            None => {
+                commands.entity(entity).despawn();
+                return;
+            }
+        };
+        let free = self.free.entry(pooled.weapon.clone()).or_default();
+        // A projectile can be spent twice in a tick, e.g. fused and swept into a mesh
+        if free.iter().any(|(free_entity, _)| *free_entity == entity) {
+            return;
+        }
+        free.push((entity, pooled.generation.wrapping_add(1)));
+        commands
+            .entity(entity)
+            .remove::<Projectile>()
+            .remove::<Bullet>()
+            .remove::<Missile>()
+            .remove::<ProjectileDetectableTag>()
+            .insert(Visibility { is_visible: false });
+    }
+
+    /// Number of spent projectiles waiting to be fired again.
+    pub fn free_count(&self) -> usize {
+        self.free.values().map(Vec::len).sum()
+    }
+}
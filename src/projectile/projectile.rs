@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::raycast::ray::Ray3d;
+use crate::{damage::health::Damage, raycast::ray::Ray3d};
 
 #[derive(Component)]
 pub struct Projectile {
@@ -9,6 +9,29 @@ pub struct Projectile {
     pub direction: Vec3,
     pub ray: Ray3d,
     pub speed: f32,
+    pub damage: Damage,
+    /// Ship that fired the projectile. Its meshes are never hit, as projectiles are fired from
+    /// inside it.
+    pub shooter: Entity,
+    /// Where the projectile was fired from.
+    pub origin: Vec3,
+    /// Translation before the last move, the start of the segment swept for hits.
+    pub previous_translation: Vec3,
+    /// Distance from the origin at which the projectile is despawned.
+    pub range: f32,
+}
+
+impl Projectile {
+    /// Moves the projectile along its velocity, keeping where it was as the start of the segment
+    /// swept for hits.
+    pub fn advance(&mut self, transform: &mut Transform, seconds: f32) {
+        self.previous_translation = transform.translation;
+        transform.translation += self.velocity * seconds;
+    }
+
+    pub fn is_out_of_range(&self, translation: Vec3) -> bool {
+        (translation - self.origin).length_squared() > self.range * self.range
+    }
 }
 
 #[derive(Component)]
@@ -0,0 +1,192 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::raycast::Cone;
+
+use super::countermeasure::CountermeasureKind;
+
+/// What a seeker homes on, and so which countermeasures fool it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SeekerKind {
+    /// Homes on heat, decoyed by flares.
+    Infrared,
+    /// Homes on radar returns, decoyed by chaff.
+    Radar,
+}
+
+impl Default for SeekerKind {
+    fn default() -> Self {
+        SeekerKind::Infrared
+    }
+}
+
+/// The sensor a guided projectile tracks its target with.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SeekerParams {
+    #[serde(default)]
+    pub kind: SeekerKind,
+    /// Half-angle in radians of the cone the seeker sees in, around the heading.
+    pub half_angle: f32,
+    pub range: f32,
+    /// Fraction of the strength of decoys the seeker ignores, from 0 to 1.
+    #[serde(default)]
+    pub counter_resistance: f32,
+}
+
+impl Default for SeekerParams {
+    fn default() -> Self {
+        SeekerParams {
+            kind: SeekerKind::default(),
+            half_angle: std::f32::consts::PI,
+            range: f32::MAX,
+            counter_resistance: 0.0,
+        }
+    }
+}
+
+/// A countermeasure seen by a seeker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decoy {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub kind: CountermeasureKind,
+    /// Chance from 0 to 1 of pulling an unresisting seeker off its target.
+    pub strength: f32,
+}
+
+impl SeekerParams {
+    pub fn cone(&self, position: Vec3, heading: Vec3) -> Cone {
+        Cone::new(position, heading, self.half_angle, self.range)
+    }
+
+    pub fn is_fooled_by(&self, kind: CountermeasureKind) -> bool {
+        matches!(
+            (self.kind, kind),
+            (SeekerKind::Infrared, CountermeasureKind::Flare)
+                | (SeekerKind::Radar, CountermeasureKind::Chaff)
+        )
+    }
+
+    /// Probability that a decoy pulls the seeker off its target.
+    pub fn attraction(&self, decoy: &Decoy) -> f32 {
+        if self.is_fooled_by(decoy.kind) {
+            (decoy.strength * (1.0 - self.counter_resistance)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Re-evaluates what the seeker tracks. The target is lost once it leaves the seeker cone.
+    /// Every decoy gets a single chance, drawn from `rng`, to pull the seeker off when it first
+    /// enters the cone; `evaluated` keeps the decoys that already had theirs.
+    pub fn reacquire(
+        &self,
+        position: Vec3,
+        heading: Vec3,
+        target: Option<(Entity, Vec3)>,
+        decoys: &[Decoy],
+        evaluated: &mut Vec<Entity>,
+        rng: &mut impl Rng,
+    ) -> Option<Entity> {
+        evaluated.retain(|entity| decoys.iter().any(|decoy| decoy.entity == *entity));
+        let cone = self.cone(position, heading);
+        let mut tracked = target
+            .filter(|(_, target_position)| cone.target(*target_position, 0.0).is_some())
+            .map(|(entity, _)| entity);
+
+        // Roll in a fixed order so a seeded rng gives the same outcome every run
+        let mut candidates: Vec<&Decoy> = decoys
+            .iter()
+            .filter(|decoy| !evaluated.contains(&decoy.entity))
+            .filter(|decoy| cone.target(decoy.position, 0.0).is_some())
+            .collect();
+        candidates.sort_by_key(|decoy| decoy.entity);
+        for decoy in candidates {
+            evaluated.push(decoy.entity);
+            if rng.gen::<f32>() < self.attraction(decoy) {
+                tracked = Some(decoy.entity);
+            }
+        }
+        tracked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const TRIALS: usize = 100;
+
+    /// Runs missiles heading at a target past a salvo of decoys, with a seeded rng, and returns
+    /// what each one tracks afterwards.
+    fn engage(seeker: SeekerParams, kind: CountermeasureKind, seed: u64) -> Vec<Option<Entity>> {
+        let target = Entity::from_raw(0);
+        let decoys: Vec<Decoy> = (1..=4)
+            .map(|index| Decoy {
+                entity: Entity::from_raw(index),
+                position: Vec3::new(index as f32, 0.0, -50.0),
+                kind,
+                strength: 0.6,
+            })
+            .collect();
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..TRIALS)
+            .map(|_| {
+                seeker.reacquire(
+                    Vec3::ZERO,
+                    -Vec3::Z,
+                    Some((target, Vec3::new(0.0, 0.0, -100.0))),
+                    &decoys,
+                    &mut Vec::new(),
+                    &mut rng,
+                )
+            })
+            .collect()
+    }
+
+    fn broken_locks(outcomes: &[Option<Entity>]) -> usize {
+        outcomes
+            .iter()
+            .filter(|&&tracked| tracked != Some(Entity::from_raw(0)))
+            .count()
+    }
+
+    #[test]
+    fn infrared_seeker_is_decoyed_by_flares() {
+        let seeker = SeekerParams::default();
+        let outcomes = engage(seeker, CountermeasureKind::Flare, 1);
+        assert!(broken_locks(&outcomes) > 0);
+    }
+
+    #[test]
+    fn radar_seeker_ignores_flares() {
+        let seeker = SeekerParams {
+            kind: SeekerKind::Radar,
+            ..Default::default()
+        };
+        let outcomes = engage(seeker, CountermeasureKind::Flare, 1);
+        assert_eq!(broken_locks(&outcomes), 0);
+    }
+
+    #[test]
+    fn full_counter_resistance_ignores_decoys() {
+        let seeker = SeekerParams {
+            counter_resistance: 1.0,
+            ..Default::default()
+        };
+        let outcomes = engage(seeker, CountermeasureKind::Flare, 1);
+        assert_eq!(broken_locks(&outcomes), 0);
+    }
+
+    #[test]
+    fn replay_gives_the_same_outcome() {
+        let seeker = SeekerParams::default();
+        assert_eq!(
+            engage(seeker, CountermeasureKind::Flare, 7),
+            engage(seeker, CountermeasureKind::Flare, 7)
+        );
+    }
+}
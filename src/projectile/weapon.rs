@@ -0,0 +1,188 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::damage::health::Damage;
+
+use super::{guidance::GuidanceParams, motor::MotorParams};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ProjectileKind {
+    Bullet,
+    Missile,
+}
+
+/// How the projectiles fired by a weapon look.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectileVisual {
+    /// Asset path of the mesh, a unit cube if missing.
+    #[serde(default)]
+    pub mesh: Option<String>,
+    pub scale: [f32; 3],
+    /// sRGBA base colour.
+    pub color: [f32; 4],
+    #[serde(default)]
+    pub unlit: bool,
+    #[serde(default)]
+    pub reflectance: f32,
+}
+
+/// A weapon loaded from a `.weapon.ron` file.
+#[derive(Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "759d5aa2-cc80-4d26-b117-884ffe65e9ed"]
+pub struct WeaponDefinition {
+    pub name: String,
+    pub kind: ProjectileKind,
+    /// Initial speed of the projectiles.
+    pub speed: f32,
+    /// Seconds between two shots.
+    pub fire_rate: f32,
+    /// Half-angle in radians of the cone the shots are randomly spread in.
+    #[serde(default)]
+    pub spread: f32,
+    /// Distance travelled before the projectiles are despawned.
+    pub range: f32,
+    pub damage: Damage,
+    pub visual: ProjectileVisual,
+    #[serde(default)]
+    pub guidance: Option<GuidanceParams>,
+    /// Flight phases of missiles, ignored by other projectiles.
+    #[serde(default)]
+    pub motor: MotorParams,
+}
+
+#[derive(Default)]
+pub struct WeaponDefinitionLoader;
+
+impl AssetLoader for WeaponDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let definition: WeaponDefinition = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(definition));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapon.ron"]
+    }
+}
+
+/// Hardpoints of the same group fire together when a [FireCommand](super::event::FireCommand)
+/// names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WeaponGroup {
+    Primary,
+    Secondary,
+}
+
+impl Default for WeaponGroup {
+    fn default() -> Self {
+        WeaponGroup::Primary
+    }
+}
+
+/// A weapon mounted on a ship.
+#[derive(Debug, Clone)]
+pub struct Hardpoint {
+    pub weapon: Handle<WeaponDefinition>,
+    pub group: WeaponGroup,
+    /// Position of the muzzle relative to the ship.
+    pub offset: Vec3,
+    /// Seconds left before the weapon can fire again.
+    pub cooldown: f32,
+}
+
+impl Hardpoint {
+    pub fn new(weapon: Handle<WeaponDefinition>, offset: Vec3) -> Self {
+        Hardpoint {
+            weapon,
+            group: WeaponGroup::default(),
+            offset,
+            cooldown: 0.0,
+        }
+    }
+
+    pub fn with_group(mut self, group: WeaponGroup) -> Self {
+        self.group = group;
+        self
+    }
+}
+
+/// The weapons carried by a ship.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Weapons {
+    pub hardpoints: Vec<Hardpoint>,
+}
+
+impl Weapons {
+    pub fn with_hardpoint(mut self, hardpoint: Hardpoint) -> Self {
+        self.hardpoints.push(hardpoint);
+        self
+    }
+}
+
+/// Mesh and material of the projectiles of each weapon, created on the first shot.
+#[derive(Default)]
+pub(crate) struct WeaponVisuals(
+    HashMap<Handle<WeaponDefinition>, (Handle<Mesh>, Handle<StandardMaterial>)>,
+);
+
+impl WeaponVisuals {
+    pub fn get_or_create(
+        &mut self,
+        weapon: &Handle<WeaponDefinition>,
+        definition: &WeaponDefinition,
+        asset_server: &AssetServer,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> (Handle<Mesh>, Handle<StandardMaterial>) {
+        self.0
+            .entry(weapon.clone())
+            .or_insert_with(|| {
+                let visual = &definition.visual;
+                let mesh = match &visual.mesh {
+                    Some(path) => asset_server.load(path.as_str()),
+                    None => meshes.add(Mesh::from(shape::Cube::default())),
+                };
+                let [r, g, b, a] = visual.color;
+                let material = materials.add(StandardMaterial {
+                    base_color: Color::rgba(r, g, b, a),
+                    reflectance: visual.reflectance,
+                    unlit: visual.unlit,
+                    ..Default::default()
+                });
+                (mesh, material)
+            })
+            .clone()
+    }
+}
+
+/// Returns a random direction within `spread` radians of `direction`, uniformly distributed over
+/// the cone.
+pub fn spread_direction(direction: Vec3, spread: f32, rng: &mut impl Rng) -> Vec3 {
+    if spread <= 0.0 {
+        return direction;
+    }
+    let cos_theta = 1.0 - rng.gen::<f32>() * (1.0 - spread.cos());
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = rng.gen::<f32>() * std::f32::consts::TAU;
+
+    let axis = if direction.x.abs() < 0.9 {
+        Vec3::X
+    } else {
+        Vec3::Y
+    };
+    let u = direction.cross(axis).normalize();
+    let v = direction.cross(u);
+    (direction * cos_theta + (u * phi.cos() + v * phi.sin()) * sin_theta).normalize()
+}
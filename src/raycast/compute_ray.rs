@@ -1,19 +1,59 @@
 use super::{ray::Ray3d, RayCastMethod, RayCastSource};
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 
+/// Updates the ray of every source. A source missing what its cast method needs, e.g. a
+/// screenspace source without a camera, has no ray and is warned about once until its ray can be
+/// computed again or the source is removed.
 #[allow(clippy::type_complexity)]
 pub fn compute_ray<T: 'static + Send + Sync>(
-    mut source_query: Query<(&mut RayCastSource<T>, Option<&GlobalTransform>)>,
+    windows: Option<Res<Windows>>,
+    mut reported_sources: Local<HashSet<Entity>>,
+    removed_sources: RemovedComponents<RayCastSource<T>>,
+    mut source_query: Query<(
+        &mut RayCastSource<T>,
+        Option<&GlobalTransform>,
+        Option<&Camera>,
+        Entity,
+    )>,
 ) {
-    for (mut source, transform) in &mut source_query.iter_mut() {
-        source.ray = match &mut source.cast_method {
-            RayCastMethod::Transform => {
-                let transform_matrix = transform
-                    .expect("The Transform has no associated GlobalTransform")
-                    .compute_matrix();
-                Some(Ray3d::from(transform_matrix))
+    for entity in removed_sources.iter() {
+        reported_sources.remove(&entity);
+    }
+    for (mut source, transform, camera, entity) in source_query.iter_mut() {
+        let ray = match &source.cast_method {
+            RayCastMethod::Transform => transform
+                .map(|transform| Ray3d::from(transform.compute_matrix()))
+                .ok_or("it has no GlobalTransform"),
+            RayCastMethod::Screenspace(cursor_position) => {
+                let window = windows.as_ref().and_then(|windows| windows.get_primary());
+                match (camera, transform, window) {
+                    (None, _, _) => Err("it has no Camera"),
+                    (_, None, _) => Err("its Camera has no GlobalTransform"),
+                    (_, _, None) => Err("there is no primary window"),
+                    (Some(camera), Some(transform), Some(window)) => Ray3d::from_viewport(
+                        camera,
+                        transform,
+                        *cursor_position,
+                        Vec2::new(window.width(), window.height()),
+                    )
+                    .ok_or("its Camera projection cannot be inverted"),
+                }
+            }
+        };
+        source.ray = match ray {
+            Ok(ray) => {
+                reported_sources.remove(&entity);
+                Some(ray)
+            }
+            Err(reason) => {
+                if reported_sources.insert(entity) {
+                    warn!(
+                        "Cannot compute the ray of RayCastSource {:?}: {}",
+                        entity, reason
+                    );
+                }
+                None
             }
-            RayCastMethod::Screenspace(_) => todo!(),
-        }
+        };
     }
 }
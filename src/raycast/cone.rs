@@ -0,0 +1,78 @@
+use bevy::{core::FloatOrd, prelude::*, render::primitives::Aabb};
+
+/// A cone used to find targets around an aim direction, e.g. within a reticle circle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cone {
+    pub apex: Vec3,
+    /// Axis of the cone, normalized by [Cone::new].
+    pub direction: Vec3,
+    /// Angle in radians between the axis and the side of the cone.
+    pub half_angle: f32,
+    pub range: f32,
+}
+
+impl Cone {
+    pub fn new(apex: Vec3, direction: Vec3, half_angle: f32, range: f32) -> Self {
+        Cone {
+            apex,
+            direction: direction.normalize(),
+            half_angle,
+            range,
+        }
+    }
+
+    /// Returns where a bounding sphere lies relative to the axis of the cone, if any part of it is
+    /// inside the cone.
+    pub fn target(&self, center: Vec3, radius: f32) -> Option<ConeTarget> {
+        let offset = center - self.apex;
+        let distance = offset.length();
+        if distance - radius > self.range {
+            return None;
+        }
+        let angle = if distance <= radius {
+            // The apex is inside the bounding sphere
+            0.0
+        } else {
+            let center_angle = self.direction.angle_between(offset);
+            (center_angle - (radius / distance).asin()).max(0.0)
+        };
+        if angle > self.half_angle {
+            return None;
+        }
+        Some(ConeTarget {
+            angle,
+            distance,
+            position: center,
+        })
+    }
+}
+
+/// An entity found by a cone or frustum query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConeTarget {
+    /// Angle in radians between the aim direction and the nearest side of the bounding sphere.
+    pub angle: f32,
+    /// Distance from the apex to the center of the bounding sphere.
+    pub distance: f32,
+    /// Center of the bounding sphere, in world space.
+    pub position: Vec3,
+}
+
+/// World space bounding sphere of an entity, from its [Aabb] if it has one.
+pub fn bounding_sphere(aabb: Option<&Aabb>, model_to_world: &Mat4) -> (Vec3, f32) {
+    match aabb {
+        Some(aabb) => {
+            let center = model_to_world.transform_point3(aabb.center.into());
+            let radius = model_to_world
+                .transform_vector3(aabb.half_extents.into())
+                .length();
+            (center, radius)
+        }
+        None => (model_to_world.transform_point3(Vec3::ZERO), 0.0),
+    }
+}
+
+/// Sorts targets by angular offset, then by distance.
+pub fn sort_targets(targets: &mut [(Entity, ConeTarget)]) {
+    targets.sort_by_key(|(_, target)| (FloatOrd(target.angle), FloatOrd(target.distance)));
+}
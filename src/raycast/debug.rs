@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::DebugLines;
+
+use super::{RayCastHits, RayCastSource};
+
+const RAY_COLOR: Color = Color::YELLOW;
+const HIT_COLOR: Color = Color::RED;
+const NORMAL_COLOR: Color = Color::CYAN;
+const TRIANGLE_COLOR: Color = Color::GREEN;
+/// Length of the drawn ray when it does not hit anything.
+const MISS_LENGTH: f32 = 1_000.0;
+const HIT_MARKER_SIZE: f32 = 0.1;
+const NORMAL_LENGTH: f32 = 1.0;
+
+/// Draws the ray of every source, and for each intersection the hit point, the hit normal and the
+/// intersected triangle. Added by [RaycastDebugPlugin](super::RaycastDebugPlugin) and runs only
+/// while [DefaultPluginState::update_debug_cursor](super::state::DefaultPluginState) is set.
+pub fn update_debug_cursor<T: 'static + Send + Sync>(
+    mut lines: ResMut<DebugLines>,
+    source_query: Query<(&RayCastSource<T>, Option<&RayCastHits<T>>)>,
+) {
+    for (source, hits) in source_query.iter() {
+        if let Some(ray) = source.ray {
+            let intersections = hits.map_or(&[][..], |hits| &hits.intersections[..]);
+            let origin = ray.position(0.0);
+            let end = intersections
+                .last()
+                .map(|(_, intersection)| intersection.position)
+                .unwrap_or_else(|| ray.position(source.max_distance.min(MISS_LENGTH)));
+            lines.line_colored(origin, end, 0.0, RAY_COLOR);
+
+            for (_, intersection) in intersections.iter() {
+                let position = intersection.position;
+                for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                    lines.line_colored(
+                        position - axis * HIT_MARKER_SIZE,
+                        position + axis * HIT_MARKER_SIZE,
+                        0.0,
+                        HIT_COLOR,
+                    );
+                }
+                lines.line_colored(
+                    position,
+                    position + intersection.normal.normalize_or_zero() * NORMAL_LENGTH,
+                    0.0,
+                    NORMAL_COLOR,
+                );
+                if let Some(triangle) = intersection.triangle {
+                    let (v0, v1, v2) = (triangle.v0.into(), triangle.v1.into(), triangle.v2.into());
+                    lines.line_colored(v0, v1, 0.0, TRIANGLE_COLOR);
+                    lines.line_colored(v1, v2, 0.0, TRIANGLE_COLOR);
+                    lines.line_colored(v2, v0, 0.0, TRIANGLE_COLOR);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    prelude::*,
+    render::mesh::{
+        skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+        VertexAttributeValues,
+    },
+};
+
+/// Morph target position offsets and their current weights. The glTF loader does not import morph
+/// targets, so this has to be filled in by whatever animates the mesh.
+#[derive(Component, Debug, Default, Clone)]
+pub struct RayCastMorphTargets {
+    /// One list of position offsets per target, with an entry for each vertex of the mesh.
+    pub targets: Vec<Vec<[f32; 3]>>,
+    pub weights: Vec<f32>,
+}
+
+/// Animated vertex positions computed during the current frame, shared between every raycast
+/// against the same mesh entity. Positions of skinned meshes are in world space, positions of
+/// meshes that are only morphed are in mesh space.
+#[derive(Default)]
+pub struct DeformedMeshes(Mutex<HashMap<Entity, Arc<Vec<[f32; 3]>>>>);
+
+impl DeformedMeshes {
+    pub fn get_or_insert_with(
+        &self,
+        entity: Entity,
+        deform: impl FnOnce() -> Option<Vec<[f32; 3]>>,
+    ) -> Option<Arc<Vec<[f32; 3]>>> {
+        if let Some(positions) = self.0.lock().unwrap().get(&entity) {
+            return Some(positions.clone());
+        }
+        // Deform outside of the lock so other meshes are not blocked in the meantime
+        let positions = Arc::new(deform()?);
+        self.0.lock().unwrap().insert(entity, positions.clone());
+        Some(positions)
+    }
+
+    pub fn clear(&mut self) {
+        self.0.get_mut().unwrap().clear();
+    }
+}
+
+pub fn clear_deformed_meshes(mut deformed_meshes: ResMut<DeformedMeshes>) {
+    deformed_meshes.clear();
+}
+
+/// Computes the joint matrices of a skin, mapping bind pose mesh space to world space.
+pub fn skin_joint_matrices(
+    skin: &SkinnedMesh,
+    inverse_bindposes: &Assets<SkinnedMeshInverseBindposes>,
+    joint_query: &Query<&GlobalTransform>,
+) -> Option<Vec<Mat4>> {
+    let inverse_bindposes = inverse_bindposes.get(&skin.inverse_bindposes)?;
+    inverse_bindposes
+        .iter()
+        .zip(skin.joints.iter())
+        .map(|(inverse_bindpose, joint)| {
+            joint_query
+                .get(*joint)
+                .ok()
+                .map(|joint| joint.compute_matrix() * *inverse_bindpose)
+        })
+        .collect()
+}
+
+/// Applies morph targets, then skinning, to the positions of the mesh on the CPU. Returns [None]
+/// if the mesh lacks the attributes needed to deform it.
+pub fn deform_positions(
+    mesh: &Mesh,
+    morph_targets: Option<&RayCastMorphTargets>,
+    joint_matrices: Option<&[Mat4]>,
+) -> Option<Vec<[f32; 3]>> {
+    let mut positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => positions.clone(),
+        _ => return None,
+    };
+
+    if let Some(morph_targets) = morph_targets {
+        for (target, weight) in morph_targets.targets.iter().zip(&morph_targets.weights) {
+            if *weight == 0.0 || target.len() != positions.len() {
+                continue;
+            }
+            for (position, offset) in positions.iter_mut().zip(target) {
+                *position = (Vec3::from(*position) + Vec3::from(*offset) * *weight).into();
+            }
+        }
+    }
+
+    if let Some(joint_matrices) = joint_matrices {
+        let (joint_indices, joint_weights) = match (
+            mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX),
+            mesh.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT),
+        ) {
+            (
+                Some(VertexAttributeValues::Uint16x4(indices)),
+                Some(VertexAttributeValues::Float32x4(weights)),
+            ) if indices.len() == positions.len() && weights.len() == positions.len() => {
+                (indices, weights)
+            }
+            _ => return None,
+        };
+
+        for ((position, indices), weights) in
+            positions.iter_mut().zip(joint_indices).zip(joint_weights)
+        {
+            let mut skin_matrix = Mat4::ZERO;
+            for (index, weight) in indices.iter().zip(weights) {
+                skin_matrix += *joint_matrices.get(*index as usize)? * *weight;
+            }
+            *position = skin_matrix.transform_point3((*position).into()).into();
+        }
+    }
+
+    Some(positions)
+}
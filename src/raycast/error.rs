@@ -0,0 +1,62 @@
+use std::{collections::HashSet, fmt, sync::Mutex};
+
+use bevy::{asset::HandleId, prelude::*, render::render_resource::PrimitiveTopology};
+
+/// Reasons a mesh cannot be raycast against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RaycastError {
+    MissingPositions,
+    UnsupportedPositionFormat,
+    UnsupportedTopology(PrimitiveTopology),
+    InvalidIndexCount(usize),
+    IndexOutOfBounds { index: usize, vertex_count: usize },
+}
+
+impl fmt::Display for RaycastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RaycastError::MissingPositions => {
+                write!(f, "mesh does not contain {}", Mesh::ATTRIBUTE_POSITION)
+            }
+            RaycastError::UnsupportedPositionFormat => {
+                write!(
+                    f,
+                    "{} is not in the Float32x3 format",
+                    Mesh::ATTRIBUTE_POSITION
+                )
+            }
+            RaycastError::UnsupportedTopology(topology) => {
+                write!(f, "primitive topology {:?} cannot be raycast", topology)
+            }
+            RaycastError::InvalidIndexCount(count) => {
+                write!(
+                    f,
+                    "triangle list has {} indices, not a multiple of 3",
+                    count
+                )
+            }
+            RaycastError::IndexOutOfBounds {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "index {} is out of bounds for {} vertices",
+                index, vertex_count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RaycastError {}
+
+/// Remembers which meshes already failed to be raycast, so each one is only warned about once.
+#[derive(Default)]
+pub struct ReportedMeshes(Mutex<HashSet<HandleId>>);
+
+impl ReportedMeshes {
+    pub fn report(&self, mesh: &Handle<Mesh>, error: &RaycastError) {
+        if self.0.lock().unwrap().insert(mesh.id) {
+            warn!("Skipping mesh {:?} in raycast: {}", mesh.id, error);
+        }
+    }
+}
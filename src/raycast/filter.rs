@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+use super::{
+    primitives::{Backfaces, IntersectionAlgorithm},
+    RayCastLayers,
+};
+
+/// Settings that decide which meshes a single raycast is able to hit.
+#[derive(Debug, Clone, Copy)]
+pub struct RayCastFilter<'a> {
+    pub backfaces: Backfaces,
+    pub algorithm: IntersectionAlgorithm,
+    pub max_distance: f32,
+    pub mask: RayCastLayers,
+    pub exclude: &'a [Entity],
+}
+
+impl<'a> Default for RayCastFilter<'a> {
+    fn default() -> Self {
+        RayCastFilter {
+            backfaces: Backfaces::default(),
+            algorithm: IntersectionAlgorithm::default(),
+            max_distance: f32::MAX,
+            mask: RayCastLayers::ALL,
+            exclude: &[],
+        }
+    }
+}
+
+impl<'a> RayCastFilter<'a> {
+    pub fn with_backfaces(mut self, backfaces: Backfaces) -> Self {
+        self.backfaces = backfaces;
+        self
+    }
+
+    pub fn with_algorithm(mut self, algorithm: IntersectionAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_mask(mut self, mask: RayCastLayers) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn with_exclude(mut self, exclude: &'a [Entity]) -> Self {
+        self.exclude = exclude;
+        self
+    }
+
+    /// Returns true if a mesh entity with the given layers passes this filter.
+    pub fn can_hit(&self, entity: Entity, layers: Option<&RayCastLayers>) -> bool {
+        self.mask
+            .intersects(layers.unwrap_or(&RayCastLayers::DEFAULT))
+            && !self.exclude.contains(&entity)
+    }
+}
@@ -0,0 +1,14 @@
+/// Specifies how many intersections a [RayCastSource](super::RayCastSource) keeps per update.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RayCastHitMode {
+    /// Only keep the nearest intersection along the ray.
+    First,
+    /// Keep every intersected entity, sorted by distance.
+    All,
+}
+
+impl Default for RayCastHitMode {
+    fn default() -> Self {
+        RayCastHitMode::All
+    }
+}
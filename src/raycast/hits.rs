@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+use super::primitives::Intersection;
+
+/// Intersections found by the [RayCastSource](super::RayCastSource) of the same entity during the
+/// last update, sorted by distance. Inserted by the plugin on every source that lacks it.
+#[derive(Component)]
+pub struct RayCastHits<T> {
+    pub intersections: Vec<(Entity, Intersection)>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for RayCastHits<T> {
+    fn default() -> Self {
+        RayCastHits {
+            intersections: Vec::new(),
+            _marker: PhantomData::default(),
+        }
+    }
+}
+
+impl<T> RayCastHits<T> {
+    /// Returns the nearest intersection.
+    pub fn first(&self) -> Option<&(Entity, Intersection)> {
+        self.intersections.first()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Entity, Intersection)> {
+        self.intersections.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intersections.is_empty()
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+
+/// Bitmask of collision layers. On a [RayCastMesh](super::RayCastMesh) entity it lists the layers
+/// the mesh belongs to; on a [RayCastSource](super::RayCastSource) it lists the layers the source
+/// is able to hit. Meshes without this component belong to [RayCastLayers::DEFAULT].
+#[derive(Component, Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RayCastLayers(pub u32);
+
+impl RayCastLayers {
+    pub const NONE: RayCastLayers = RayCastLayers(0);
+    pub const DEFAULT: RayCastLayers = RayCastLayers(1);
+    pub const ALL: RayCastLayers = RayCastLayers(u32::MAX);
+
+    /// Creates a mask containing only the given layer, or [None] if the layer is outside the
+    /// range `0..32`.
+    pub fn layer(layer: u8) -> Option<Self> {
+        RayCastLayers::NONE.with(layer)
+    }
+
+    /// Adds a layer, or returns [None] if the layer is outside the range `0..32`.
+    pub fn with(self, layer: u8) -> Option<Self> {
+        Self::bit(layer).map(|bit| RayCastLayers(self.0 | bit))
+    }
+
+    /// Removes a layer, or returns [None] if the layer is outside the range `0..32`.
+    pub fn without(self, layer: u8) -> Option<Self> {
+        Self::bit(layer).map(|bit| RayCastLayers(self.0 & !bit))
+    }
+
+    fn bit(layer: u8) -> Option<u32> {
+        1u32.checked_shl(layer as u32)
+    }
+
+    pub fn intersects(&self, other: &RayCastLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for RayCastLayers {
+    fn default() -> Self {
+        RayCastLayers::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_in_range() {
+        let layers = RayCastLayers::layer(0).and_then(|layers| layers.with(31));
+        assert_eq!(layers, Some(RayCastLayers(1 | 1 << 31)));
+        assert_eq!(
+            layers.and_then(|layers| layers.without(0)),
+            Some(RayCastLayers(1 << 31))
+        );
+    }
+
+    #[test]
+    fn layers_out_of_range() {
+        assert_eq!(RayCastLayers::layer(32), None);
+        assert_eq!(RayCastLayers::ALL.with(40), None);
+        assert_eq!(RayCastLayers::ALL.without(u8::MAX), None);
+    }
+}
@@ -1,22 +1,44 @@
 pub mod compute_ray;
+pub mod cone;
+pub mod debug;
+pub mod deform;
+pub mod error;
 pub mod event;
+pub mod filter;
+pub mod hit_mode;
+pub mod hits;
 pub mod label;
+pub mod layers;
 pub mod mesh;
 pub mod method;
+pub mod packet;
+pub mod parallel;
 pub mod primitives;
 pub mod ray;
+pub mod ray_caster;
+pub mod shape_cast;
 pub mod source;
 pub mod state;
+pub mod triangles;
 pub mod update_raycast;
 
-use bevy::prelude::*;
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
 use std::marker::PhantomData;
 
+pub use cone::*;
+pub use error::*;
+pub use filter::*;
+pub use hit_mode::*;
+pub use hits::*;
+pub use layers::*;
 pub use mesh::*;
 pub use method::*;
+pub use ray_caster::*;
 pub use source::*;
 
 use compute_ray::*;
+use debug::*;
+use deform::*;
 use label::*;
 use state::*;
 use update_raycast::*;
@@ -27,6 +49,10 @@ pub struct RaycastPlugin<T: 'static + Send + Sync>(pub PhantomData<T>);
 
 impl<T: 'static + Send + Sync> Plugin for RaycastPlugin<T> {
     fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<DeformedMeshes>() {
+            app.init_resource::<DeformedMeshes>()
+                .add_system_to_stage(CoreStage::First, clear_deformed_meshes);
+        }
         app.init_resource::<DefaultPluginState<T>>()
             .add_event::<HoverEvent>()
             .add_system_set_to_stage(
@@ -56,3 +82,32 @@ impl<T: 'static + Send + Sync> Default for RaycastPlugin<T> {
         RaycastPlugin(PhantomData::<T>)
     }
 }
+
+/// Draws the rays and intersections of the set while
+/// [DefaultPluginState::update_debug_cursor] is set. Requires [RaycastPlugin] and
+/// [DebugLinesPlugin](bevy_prototype_debug_lines::DebugLinesPlugin) to be added separately.
+pub struct RaycastDebugPlugin<T: 'static + Send + Sync>(pub PhantomData<T>);
+
+impl<T: 'static + Send + Sync> Plugin for RaycastDebugPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            update_debug_cursor::<T>
+                .label(RaycastSystem::UpdateDebugCursor)
+                .with_run_criteria(|state: Res<DefaultPluginState<T>>| {
+                    if state.update_debug_cursor {
+                        ShouldRun::Yes
+                    } else {
+                        ShouldRun::No
+                    }
+                })
+                .after(RaycastSystem::UpdateRaycast),
+        );
+    }
+}
+
+impl<T: 'static + Send + Sync> Default for RaycastDebugPlugin<T> {
+    fn default() -> Self {
+        RaycastDebugPlugin(PhantomData::<T>)
+    }
+}
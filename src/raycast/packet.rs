@@ -0,0 +1,257 @@
+use std::f32::EPSILON;
+
+use bevy::{math::Vec4, prelude::*};
+
+use super::{
+    primitives::{Backfaces, Intersection, IntersectionAlgorithm, Triangle},
+    ray::Ray3d,
+    triangles::MeshTriangles,
+    update_raycast::{mesh_to_world_intersection, triangle_intersection},
+};
+
+/// Number of rays tested at once by a [RayPacket4].
+pub const PACKET_WIDTH: usize = 4;
+
+/// Rays stored so that a ray-triangle test runs on all of them at once.
+pub trait RayPacket: Sized {
+    /// Number of rays the packet holds.
+    const WIDTH: usize;
+
+    /// Packs up to [RayPacket::WIDTH] rays, further rays are ignored.
+    fn new(rays: &[Ray3d]) -> Self;
+
+    /// Tests every ray against the triangle with Möller-Trumbore. `distances` holds the nearest
+    /// distance of each lane so far and is lowered for the lanes hitting the triangle closer,
+    /// whose bits are set in the returned mask.
+    fn intersect(&self, triangle: &Triangle, backfaces: Backfaces, distances: &mut [f32]) -> u32;
+}
+
+/// Up to four rays stored component-wise, so each [Vec4] holds the same component of every ray
+/// and one ray-triangle test runs on all of them in SIMD lanes.
+#[derive(Debug, Clone, Copy)]
+pub struct RayPacket4 {
+    origin: [Vec4; 3],
+    direction: [Vec4; 3],
+    /// Bitmask of the lanes holding a ray, the others are padding.
+    active: u32,
+}
+
+impl RayPacket for RayPacket4 {
+    const WIDTH: usize = PACKET_WIDTH;
+
+    fn new(rays: &[Ray3d]) -> Self {
+        let mut origin = [[0.0; PACKET_WIDTH]; 3];
+        let mut direction = [[0.0; PACKET_WIDTH]; 3];
+        let mut active = 0;
+        for (lane, ray) in rays.iter().take(PACKET_WIDTH).enumerate() {
+            let ray_origin: [f32; 3] = ray.origin.into();
+            let ray_direction: [f32; 3] = ray.direction.into();
+            for axis in 0..3 {
+                origin[axis][lane] = ray_origin[axis];
+                direction[axis][lane] = ray_direction[axis];
+            }
+            active |= 1 << lane;
+        }
+        RayPacket4 {
+            origin: origin.map(Vec4::from),
+            direction: direction.map(Vec4::from),
+            active,
+        }
+    }
+
+    fn intersect(&self, triangle: &Triangle, backfaces: Backfaces, distances: &mut [f32]) -> u32 {
+        let hit =
+            raycast_packet_moller_trumbore(self, triangle, backfaces, Vec4::from_slice(distances));
+        let hit_distances: [f32; PACKET_WIDTH] = hit.distance.into();
+        for lane in (0..PACKET_WIDTH).filter(|&lane| hit.is_hit(lane)) {
+            distances[lane] = hit_distances[lane];
+        }
+        hit.mask
+    }
+}
+
+impl RayPacket4 {
+    pub fn len(&self) -> usize {
+        self.active.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active == 0
+    }
+}
+
+/// Up to eight rays as a pair of [RayPacket4]s tested one after the other. The lanes are still
+/// four wide; the pair only amortizes loading the triangle over more rays.
+#[derive(Debug, Clone, Copy)]
+pub struct RayPacket4x2 {
+    halves: [RayPacket4; 2],
+}
+
+impl RayPacket for RayPacket4x2 {
+    const WIDTH: usize = 2 * PACKET_WIDTH;
+
+    fn new(rays: &[Ray3d]) -> Self {
+        let split = rays.len().min(PACKET_WIDTH);
+        RayPacket4x2 {
+            halves: [
+                RayPacket4::new(&rays[..split]),
+                RayPacket4::new(&rays[split..]),
+            ],
+        }
+    }
+
+    fn intersect(&self, triangle: &Triangle, backfaces: Backfaces, distances: &mut [f32]) -> u32 {
+        let (low, high) = distances.split_at_mut(PACKET_WIDTH);
+        let low_mask = if self.halves[0].is_empty() {
+            0
+        } else {
+            self.halves[0].intersect(triangle, backfaces, low)
+        };
+        let high_mask = if self.halves[1].is_empty() {
+            0
+        } else {
+            self.halves[1].intersect(triangle, backfaces, high)
+        };
+        low_mask | high_mask << PACKET_WIDTH
+    }
+}
+
+impl RayPacket4x2 {
+    pub fn len(&self) -> usize {
+        self.halves.iter().map(RayPacket4::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Result of a [RayPacket4] triangle test. Lanes are only valid where `mask` has their bit set.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketHit4 {
+    pub distance: Vec4,
+    pub uv_coords: (Vec4, Vec4),
+    pub mask: u32,
+}
+
+impl PacketHit4 {
+    pub fn is_hit(&self, lane: usize) -> bool {
+        self.mask & 1 << lane != 0
+    }
+}
+
+/// Möller-Trumbore ray-triangle intersection test run on the four rays of the packet at once.
+/// Only hits closer than the per lane `max_distance` are kept.
+pub fn raycast_packet_moller_trumbore(
+    packet: &RayPacket4,
+    triangle: &Triangle,
+    backface_culling: Backfaces,
+    max_distance: Vec4,
+) -> PacketHit4 {
+    let v0_to_v1 = triangle.v1 - triangle.v0;
+    let v0_to_v2 = triangle.v2 - triangle.v0;
+    let [e1x, e1y, e1z] = [
+        Vec4::splat(v0_to_v1.x),
+        Vec4::splat(v0_to_v1.y),
+        Vec4::splat(v0_to_v1.z),
+    ];
+    let [e2x, e2y, e2z] = [
+        Vec4::splat(v0_to_v2.x),
+        Vec4::splat(v0_to_v2.y),
+        Vec4::splat(v0_to_v2.z),
+    ];
+    let [dx, dy, dz] = packet.direction;
+
+    // p = direction x (v2 - v0)
+    let px = dy * e2z - dz * e2y;
+    let py = dz * e2x - dx * e2z;
+    let pz = dx * e2y - dy * e2x;
+    let determinant = e1x * px + e1y * py + e1z * pz;
+
+    let determinant_mask = match backface_culling {
+        Backfaces::Cull => determinant.cmpgt(Vec4::splat(EPSILON)),
+        Backfaces::Include => determinant.abs().cmpgt(Vec4::splat(EPSILON)),
+    };
+    // Lanes with a zero determinant become NaN or infinite, and are rejected by the mask
+    let determinant_inverse = Vec4::ONE / determinant;
+
+    let tx = packet.origin[0] - Vec4::splat(triangle.v0.x);
+    let ty = packet.origin[1] - Vec4::splat(triangle.v0.y);
+    let tz = packet.origin[2] - Vec4::splat(triangle.v0.z);
+    let u = (tx * px + ty * py + tz * pz) * determinant_inverse;
+
+    // q = t x (v1 - v0)
+    let qx = ty * e1z - tz * e1y;
+    let qy = tz * e1x - tx * e1z;
+    let qz = tx * e1y - ty * e1x;
+    let v = (dx * qx + dy * qy + dz * qz) * determinant_inverse;
+    let t = (e2x * qx + e2y * qy + e2z * qz) * determinant_inverse;
+
+    let mask = determinant_mask
+        & u.cmpge(Vec4::ZERO)
+        & u.cmple(Vec4::ONE)
+        & v.cmpge(Vec4::ZERO)
+        & (u + v).cmple(Vec4::ONE)
+        & t.cmpgt(Vec4::ZERO)
+        & t.cmplt(max_distance);
+
+    PacketHit4 {
+        distance: t,
+        uv_coords: (u, v),
+        mask: mask.bitmask() & packet.active,
+    }
+}
+
+/// Casts every ray against the triangles of a mesh, a packet of rays at a time, and returns the
+/// nearest intersection of each ray in the same order. Uses Möller-Trumbore for the packet tests.
+pub fn compute_packet_intersections<P: RayPacket>(
+    mesh_to_world: &Mat4,
+    triangles: &MeshTriangles,
+    rays: &[Ray3d],
+    backfaces: Backfaces,
+    max_distance: f32,
+) -> Vec<Option<Intersection>> {
+    let world_to_mesh = mesh_to_world.inverse();
+    let mesh_space_rays: Vec<Ray3d> = rays
+        .iter()
+        .map(|ray| ray.transform(&world_to_mesh))
+        .collect();
+    let packets: Vec<P> = mesh_space_rays.chunks(P::WIDTH).map(P::new).collect();
+
+    // Nearest mesh space distance and triangle of every ray, padded to whole packets
+    let mut min_distances = vec![f32::MAX; packets.len() * P::WIDTH];
+    let mut nearest_triangles = vec![None; packets.len() * P::WIDTH];
+
+    for (triangle, vertices) in triangles.iter() {
+        let triangle_positions = Triangle::from(triangles.triangle_positions(vertices));
+        for ((packet, distances), nearest) in packets
+            .iter()
+            .zip(min_distances.chunks_mut(P::WIDTH))
+            .zip(nearest_triangles.chunks_mut(P::WIDTH))
+        {
+            let mask = packet.intersect(&triangle_positions, backfaces, distances);
+            for lane in (0..P::WIDTH).filter(|&lane| mask & 1 << lane != 0) {
+                nearest[lane] = Some((triangle, vertices));
+            }
+        }
+    }
+
+    // Build the full intersection of the nearest triangle with the scalar path
+    mesh_space_rays
+        .iter()
+        .zip(nearest_triangles)
+        .map(|(mesh_space_ray, nearest_triangle)| {
+            let (triangle, vertices) = nearest_triangle?;
+            let intersection = triangle_intersection(
+                triangles.triangle_positions(vertices),
+                triangles.triangle_normals(vertices),
+                f32::MAX,
+                *mesh_space_ray,
+                backfaces,
+                IntersectionAlgorithm::MollerTrumbore,
+            )?;
+            mesh_to_world_intersection(mesh_to_world, mesh_space_ray, intersection, max_distance)
+                .map(|intersection| triangles.interpolate(intersection, triangle, vertices))
+        })
+        .collect()
+}
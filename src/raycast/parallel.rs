@@ -0,0 +1,38 @@
+use bevy::{core::FloatOrd, prelude::*, tasks::TaskPool};
+
+use super::primitives::Intersection;
+
+/// Runs `f` on the items in parallel, one task per chunk of items. Each task collects its results
+/// in its own buffer, so no lock is shared between them, and the buffers are concatenated in the
+/// order of the items.
+pub fn par_filter_map<I, R>(
+    task_pool: &TaskPool,
+    items: &[I],
+    f: impl Fn(&I) -> Option<R> + Sync,
+) -> Vec<R>
+where
+    I: Sync,
+    R: Send + 'static,
+{
+    if items.is_empty() {
+        return Vec::new();
+    }
+    let thread_num = task_pool.thread_num().max(1);
+    let chunk_size = (items.len() + thread_num - 1) / thread_num;
+    let f = &f;
+    task_pool
+        .scope(|scope| {
+            for chunk in items.chunks(chunk_size) {
+                scope.spawn(async move { chunk.iter().filter_map(f).collect::<Vec<_>>() });
+            }
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Sorts intersections by distance. Equal distances are all kept and ordered by entity, so the
+/// order is the same for the same intersections whatever thread found them.
+pub fn sort_by_distance(picks: &mut [(Entity, Intersection)]) {
+    picks.sort_by_key(|(entity, intersection)| (FloatOrd(intersection.distance()), *entity));
+}
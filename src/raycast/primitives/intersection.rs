@@ -9,6 +9,14 @@ pub struct Intersection {
     pub normal: Vec3,
     pub distance: f32,
     pub triangle: Option<Triangle>,
+    /// Index of the intersected triangle within its mesh.
+    pub triangle_index: Option<usize>,
+    /// Weights of the three vertices of the triangle at the intersection point.
+    pub barycentric_coords: Vec3,
+    /// [Mesh::ATTRIBUTE_UV_0] interpolated at the intersection point.
+    pub uv: Option<Vec2>,
+    /// [Mesh::ATTRIBUTE_COLOR] interpolated at the intersection point.
+    pub color: Option<Color>,
 }
 
 impl Intersection {
@@ -23,10 +31,49 @@ impl Intersection {
             normal,
             distance: pick_distance,
             triangle,
+            triangle_index: None,
+            barycentric_coords: Vec3::ZERO,
+            uv: None,
+            color: None,
         }
     }
 
+    pub fn with_barycentric_coords(mut self, barycentric_coords: Vec3) -> Self {
+        self.barycentric_coords = barycentric_coords;
+        self
+    }
+
     pub fn distance(&self) -> f32 {
         self.distance
     }
+
+    /// Interpolates per vertex values of the triangle at the intersection point.
+    pub fn interpolate<V>(&self, values: [V; 3]) -> V
+    where
+        V: std::ops::Mul<f32, Output = V> + std::ops::Add<Output = V>,
+    {
+        let [v0, v1, v2] = values;
+        v0 * self.barycentric_coords.x
+            + v1 * self.barycentric_coords.y
+            + v2 * self.barycentric_coords.z
+    }
+}
+
+/// Barycentric coordinates of a point in the plane of the triangle.
+pub fn barycentric_coords(point: Vec3A, triangle: &Triangle) -> Vec3 {
+    let v0_to_v1 = triangle.v1 - triangle.v0;
+    let v0_to_v2 = triangle.v2 - triangle.v0;
+    let v0_to_point = point - triangle.v0;
+    let d00 = v0_to_v1.dot(v0_to_v1);
+    let d01 = v0_to_v1.dot(v0_to_v2);
+    let d11 = v0_to_v2.dot(v0_to_v2);
+    let d20 = v0_to_point.dot(v0_to_v1);
+    let d21 = v0_to_point.dot(v0_to_v2);
+    let denominator = d00 * d11 - d01 * d01;
+    if denominator.abs() < f32::EPSILON {
+        return Vec3::X;
+    }
+    let v = (d11 * d20 - d01 * d21) / denominator;
+    let w = (d00 * d21 - d01 * d20) / denominator;
+    Vec3::new(1.0 - v - w, v, w)
 }
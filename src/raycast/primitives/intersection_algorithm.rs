@@ -0,0 +1,16 @@
+/// Algorithm used to intersect a ray with a triangle.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum IntersectionAlgorithm {
+    /// Möller-Trumbore, fast but rays can slip through the shared edges of adjacent triangles
+    /// and grazing hits are dropped.
+    MollerTrumbore,
+    /// Watertight intersection from Woop, Benthin and Wald, which never misses a ray hitting an
+    /// edge or vertex shared by several triangles.
+    Watertight,
+}
+
+impl Default for IntersectionAlgorithm {
+    fn default() -> Self {
+        IntersectionAlgorithm::MollerTrumbore
+    }
+}
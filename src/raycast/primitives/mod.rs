@@ -1,5 +1,6 @@
 mod backfaces;
 mod intersection;
+mod intersection_algorithm;
 mod into_usize;
 mod ray_hit;
 mod triangle;
@@ -7,6 +8,7 @@ mod triangle_trait;
 
 pub use backfaces::*;
 pub use intersection::*;
+pub use intersection_algorithm::*;
 pub use into_usize::*;
 pub use ray_hit::*;
 pub use triangle::*;
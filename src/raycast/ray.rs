@@ -1,6 +1,7 @@
 use bevy::{
-    math::{Mat4, Vec3, Vec3A},
-    render::primitives::Aabb,
+    math::{Mat4, Vec2, Vec3, Vec3A},
+    render::{camera::Camera, primitives::Aabb},
+    transform::components::GlobalTransform,
 };
 
 /// A 3D ray, with an origin and direction. The direction is guaranteed to be normalized.
@@ -18,6 +19,31 @@ impl Ray3d {
         }
     }
 
+    /// Builds the ray going through a point of the viewport of a camera, in logical pixels from
+    /// the bottom left corner. Returns [None] if the camera projection cannot be inverted.
+    pub fn from_viewport(
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        viewport_position: Vec2,
+        viewport_size: Vec2,
+    ) -> Option<Self> {
+        let ndc = viewport_position / viewport_size * 2.0 - Vec2::ONE;
+        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+        // Bevy uses a reversed depth buffer, so the near plane is at 1. The far plane of a
+        // perspective projection is at infinity, so the second point is taken halfway instead.
+        let near = ndc_to_world.project_point3(ndc.extend(1.0));
+        let far = ndc_to_world.project_point3(ndc.extend(0.5));
+        let direction = far - near;
+        if !near.is_finite() || !direction.is_finite() || direction.length_squared() == 0.0 {
+            return None;
+        }
+        Some(Ray3d::new(near, direction))
+    }
+
+    pub fn origin(&self) -> Vec3 {
+        self.origin.into()
+    }
+
     pub fn direction(self: Ray3d) -> Vec3 {
         self.direction.into()
     }
@@ -26,6 +52,117 @@ impl Ray3d {
         (self.origin + self.direction * distance).into()
     }
 
+    /// Returns the ray transformed by the matrix, e.g. from world space to mesh space. The
+    /// direction is normalized again, so distances along the new ray are in the new space.
+    pub fn transform(&self, transform: &Mat4) -> Self {
+        Ray3d::new(
+            transform.transform_point3(self.origin.into()),
+            transform.transform_vector3(self.direction.into()),
+        )
+    }
+
+    /// Distance along the ray of the point of the ray closest to `point`.
+    pub fn closest_distance(&self, point: Vec3) -> f32 {
+        (Vec3A::from(point) - self.origin)
+            .dot(self.direction)
+            .max(0.0)
+    }
+
+    /// Point of the ray closest to `point`.
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        self.position(self.closest_distance(point))
+    }
+
+    pub fn distance_to_point(&self, point: Vec3) -> f32 {
+        self.closest_point(point).distance(point)
+    }
+
+    /// Closest points between the ray and the segment, as `[on_ray, on_segment]`.
+    pub fn closest_points_to_segment(&self, start: Vec3, end: Vec3) -> [Vec3; 2] {
+        let start = Vec3A::from(start);
+        let [t, s] = self.closest_parameters(start, Vec3A::from(end) - start, Some(1.0));
+        [
+            self.position(t),
+            (start + (Vec3A::from(end) - start) * s).into(),
+        ]
+    }
+
+    /// Closest points between the two rays, as `[on_self, on_other]`.
+    pub fn closest_points_to_ray(&self, other: &Ray3d) -> [Vec3; 2] {
+        let [t, s] = self.closest_parameters(other.origin, other.direction, None);
+        [self.position(t), other.position(s)]
+    }
+
+    /// Parameters of the closest points between the ray and the line `start + s * direction`,
+    /// both clamped to be positive and `s` clamped to `max_s` if given.
+    fn closest_parameters(&self, start: Vec3A, direction: Vec3A, max_s: Option<f32>) -> [f32; 2] {
+        let clamp_s = |s: f32| match max_s {
+            Some(max_s) => s.clamp(0.0, max_s),
+            None => s.max(0.0),
+        };
+        let offset = self.origin - start;
+        let b = self.direction.dot(direction);
+        let c = direction.length_squared();
+        let d = self.direction.dot(offset);
+        let e = direction.dot(offset);
+        if c <= f32::EPSILON {
+            // Degenerate segment, closest to its start
+            return [(-d).max(0.0), 0.0];
+        }
+
+        // Closest points of the infinite lines, or any point if they are parallel
+        let denominator = c - b * b;
+        let mut s = if denominator > f32::EPSILON {
+            clamp_s((e - b * d) / denominator)
+        } else {
+            0.0
+        };
+        let mut t = b * s - d;
+        if t < 0.0 {
+            // Behind the origin, so the closest point of the other line to the origin
+            t = 0.0;
+            s = clamp_s(e / c);
+        } else {
+            s = clamp_s((t * b + e) / c);
+            t = (b * s - d).max(0.0);
+        }
+        [t, s]
+    }
+
+    /// Distance along the ray of its intersection with the plane, if it hits it in front of its
+    /// origin.
+    pub fn intersects_plane(&self, plane_origin: Vec3, plane_normal: Vec3) -> Option<f32> {
+        let plane_normal = Vec3A::from(plane_normal);
+        let denominator = self.direction.dot(plane_normal);
+        if denominator.abs() <= f32::EPSILON {
+            return None;
+        }
+        let distance = (Vec3A::from(plane_origin) - self.origin).dot(plane_normal) / denominator;
+        if distance >= 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+
+    /// Distances along the ray where it enters and leaves the sphere. The entry distance is
+    /// negative if the origin is inside the sphere.
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> Option<[f32; 2]> {
+        let offset = self.origin - Vec3A::from(center);
+        let b = offset.dot(self.direction);
+        let c = offset.length_squared() - radius * radius;
+        let discriminant = b * b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let far = -b + root;
+        if far < 0.0 {
+            return None;
+        }
+        Some([-b - root, far])
+    }
+
     pub fn intersects_aabb(&self, aabb: &Aabb, model_to_world: &Mat4) -> Option<[f32; 2]> {
         let world_to_model = model_to_world.inverse();
         let ray_dir: Vec3A = world_to_model
@@ -0,0 +1,419 @@
+use std::sync::Arc;
+
+use bevy::{
+    core::FloatOrd,
+    ecs::system::SystemParam,
+    prelude::*,
+    render::{
+        mesh::skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+        primitives::{Aabb, Frustum},
+    },
+    tasks::ComputeTaskPool,
+};
+
+use super::{
+    cone::{bounding_sphere, sort_targets, Cone, ConeTarget},
+    deform::{deform_positions, skin_joint_matrices, DeformedMeshes, RayCastMorphTargets},
+    error::ReportedMeshes,
+    packet::{compute_packet_intersections, RayPacket},
+    parallel::{par_filter_map, sort_by_distance},
+    primitives::{Intersection, IntersectionAlgorithm},
+    ray::Ray3d,
+    shape_cast::{compute_shape_triangles_intersection, swept_aabb_intersection, CastShape},
+    state::DefaultPluginState,
+    triangles::MeshTriangles,
+    update_raycast::compute_triangles_intersection,
+    RayCastFilter, RayCastLayers, RayCastMesh,
+};
+
+/// Casts rays against every [RayCastMesh] of the set `T` from inside any system, without the need
+/// for a [RayCastSource](super::RayCastSource) entity.
+#[derive(SystemParam)]
+pub struct RayCaster<'w, 's, T: 'static + Send + Sync> {
+    meshes: Res<'w, Assets<Mesh>>,
+    task_pool: Res<'w, ComputeTaskPool>,
+    state: Res<'w, DefaultPluginState<T>>,
+    deformed_meshes: Res<'w, DeformedMeshes>,
+    inverse_bindposes: Option<Res<'w, Assets<SkinnedMeshInverseBindposes>>>,
+    culling_query: Query<
+        'w,
+        's,
+        (
+            &'static Visibility,
+            Option<&'static Aabb>,
+            &'static GlobalTransform,
+            Option<&'static RayCastLayers>,
+            Option<&'static SkinnedMesh>,
+            Entity,
+        ),
+        With<RayCastMesh<T>>,
+    >,
+    mesh_query: Query<
+        'w,
+        's,
+        (
+            &'static Handle<Mesh>,
+            &'static GlobalTransform,
+            Option<&'static SkinnedMesh>,
+            Option<&'static RayCastMorphTargets>,
+            Entity,
+        ),
+        With<RayCastMesh<T>>,
+    >,
+    transform_query: Query<'w, 's, &'static GlobalTransform>,
+    children_query: Query<'w, 's, &'static Children>,
+    reported_meshes: Local<'s, ReportedMeshes>,
+}
+
+impl<'w, 's, T: 'static + Send + Sync> RayCaster<'w, 's, T> {
+    /// Returns the nearest intersection along the ray, if any.
+    pub fn cast(&self, ray: &Ray3d, filter: &RayCastFilter) -> Option<(Entity, Intersection)> {
+        self.cast_all(ray, filter).into_iter().next()
+    }
+
+    /// Returns every intersected entity, sorted by distance along the ray.
+    pub fn cast_all(&self, ray: &Ray3d, filter: &RayCastFilter) -> Vec<(Entity, Intersection)> {
+        let culled_entities = self.culled_entities(filter, |aabb, model_to_world| {
+            ray.intersects_aabb(aabb, model_to_world)
+        });
+        if culled_entities.is_empty() {
+            return Vec::new();
+        }
+
+        self.intersect_meshes(&culled_entities, |triangles, mesh_to_world| {
+            compute_triangles_intersection(
+                mesh_to_world,
+                triangles,
+                ray,
+                filter.backfaces,
+                filter.algorithm,
+                filter.max_distance,
+            )
+        })
+    }
+
+    /// Returns the nearest intersection of each ray, in the same order as the rays. Rays are
+    /// tested against each triangle in packets of `P`, e.g.
+    /// [RayPacket4x2](super::packet::RayPacket4x2), which scales much better than separate
+    /// [RayCaster::cast] calls when casting many rays.
+    /// Falls back to one ray at a time if the filter asks for the watertight algorithm.
+    pub fn cast_packet<P: RayPacket>(
+        &self,
+        rays: &[Ray3d],
+        filter: &RayCastFilter,
+    ) -> Vec<Option<(Entity, Intersection)>> {
+        if filter.algorithm != IntersectionAlgorithm::MollerTrumbore {
+            return rays.iter().map(|ray| self.cast(ray, filter)).collect();
+        }
+
+        let culled_entities = self.culled_entities(filter, |aabb, model_to_world| {
+            rays.iter()
+                .filter_map(|ray| ray.intersects_aabb(aabb, model_to_world))
+                .reduce(|[near, far], [other_near, other_far]| {
+                    [near.min(other_near), far.max(other_far)]
+                })
+        });
+        if culled_entities.is_empty() {
+            return vec![None; rays.len()];
+        }
+
+        let mesh_intersections =
+            self.par_map_meshes(&culled_entities, |entity, triangles, mesh_to_world| {
+                let intersections = compute_packet_intersections::<P>(
+                    mesh_to_world,
+                    triangles,
+                    rays,
+                    filter.backfaces,
+                    filter.max_distance,
+                );
+                Some((entity, intersections))
+            });
+
+        // Keep the nearest hit of each ray, preferring the lowest entity on ties
+        let mut picks: Vec<Option<(Entity, Intersection)>> = vec![None; rays.len()];
+        for (entity, intersections) in mesh_intersections {
+            for (pick, intersection) in picks.iter_mut().zip(intersections) {
+                if let Some(intersection) = intersection {
+                    let key = (FloatOrd(intersection.distance()), entity);
+                    match pick {
+                        Some((nearest_entity, nearest))
+                            if (FloatOrd(nearest.distance()), *nearest_entity) <= key => {}
+                        _ => *pick = Some((entity, intersection)),
+                    }
+                }
+            }
+        }
+        picks
+    }
+
+    /// Sweeps a sphere along the ray and returns the first mesh it touches.
+    pub fn sphere_cast(
+        &self,
+        ray: &Ray3d,
+        radius: f32,
+        filter: &RayCastFilter,
+    ) -> Option<(Entity, Intersection)> {
+        self.shape_cast(ray, &CastShape::Sphere { radius }, filter)
+    }
+
+    /// Sweeps an oriented box along the ray and returns the first mesh it touches.
+    pub fn box_cast(
+        &self,
+        ray: &Ray3d,
+        half_extents: Vec3,
+        rotation: Quat,
+        filter: &RayCastFilter,
+    ) -> Option<(Entity, Intersection)> {
+        self.shape_cast(
+            ray,
+            &CastShape::Cuboid {
+                half_extents,
+                rotation,
+            },
+            filter,
+        )
+    }
+
+    /// Sweeps the shape along the ray and returns the first mesh it touches. The distance of the
+    /// intersection is the distance travelled by the shape until the time of impact.
+    pub fn shape_cast(
+        &self,
+        ray: &Ray3d,
+        shape: &CastShape,
+        filter: &RayCastFilter,
+    ) -> Option<(Entity, Intersection)> {
+        let culled_entities = self.culled_entities(filter, |aabb, model_to_world| {
+            swept_aabb_intersection(ray, aabb, model_to_world, shape)
+        });
+        if culled_entities.is_empty() {
+            return None;
+        }
+
+        self.intersect_meshes(&culled_entities, |triangles, mesh_to_world| {
+            compute_shape_triangles_intersection(
+                triangles,
+                mesh_to_world,
+                ray,
+                shape,
+                filter.max_distance,
+                filter.backfaces,
+            )
+        })
+        .into_iter()
+        .next()
+    }
+
+    /// Returns every entity whose bounding sphere is at least partly inside the cone, sorted by
+    /// angular offset from the axis of the cone, then by distance.
+    pub fn cone_query(&self, cone: &Cone, filter: &RayCastFilter) -> Vec<(Entity, ConeTarget)> {
+        let cone = Cone {
+            range: cone.range.min(filter.max_distance),
+            ..*cone
+        };
+        let mut targets: Vec<_> = self
+            .culling_query
+            .iter()
+            .filter(|(visibility, _, _, layers, _, entity)| {
+                visibility.is_visible && filter.can_hit(*entity, *layers)
+            })
+            .filter_map(|(_, aabb, transform, _, _, entity)| {
+                let (center, radius) = bounding_sphere(aabb, &transform.compute_matrix());
+                cone.target(center, radius).map(|target| (entity, target))
+            })
+            .collect();
+        sort_targets(&mut targets);
+        targets
+    }
+
+    /// Returns every entity inside the frustum of a camera, sorted by angular offset from the
+    /// forward direction of the camera, then by distance.
+    pub fn frustum_query(
+        &self,
+        frustum: &Frustum,
+        camera_transform: &GlobalTransform,
+        filter: &RayCastFilter,
+    ) -> Vec<(Entity, ConeTarget)> {
+        let aim = Cone::new(
+            camera_transform.translation,
+            camera_transform.rotation * -Vec3::Z,
+            std::f32::consts::PI,
+            filter.max_distance,
+        );
+        let mut targets: Vec<_> = self
+            .culling_query
+            .iter()
+            .filter(|(visibility, _, _, layers, _, entity)| {
+                visibility.is_visible && filter.can_hit(*entity, *layers)
+            })
+            .filter_map(|(_, aabb, transform, _, _, entity)| {
+                let model_to_world = transform.compute_matrix();
+                if let Some(aabb) = aabb {
+                    if !frustum.intersects_obb(aabb, &model_to_world) {
+                        return None;
+                    }
+                }
+                let (center, radius) = bounding_sphere(aabb, &model_to_world);
+                aim.target(center, radius).map(|target| (entity, target))
+            })
+            .collect();
+        sort_targets(&mut targets);
+        targets
+    }
+
+    /// Returns true if no mesh passing the filter lies on the segment between the two points. The
+    /// meshes of the `ignore` entities and of their descendants are never occluders.
+    pub fn line_of_sight(
+        &self,
+        from: Vec3,
+        to: Vec3,
+        ignore: &[Entity],
+        filter: &RayCastFilter,
+    ) -> bool {
+        let offset = to - from;
+        let distance = offset.length();
+        if distance <= f32::EPSILON {
+            return true;
+        }
+        let mut exclude = filter.exclude.to_vec();
+        for entity in ignore {
+            self.push_descendants(*entity, &mut exclude);
+        }
+        let filter = filter
+            .with_exclude(&exclude)
+            .with_max_distance(distance.min(filter.max_distance));
+        self.cast(&Ray3d::new(from, offset), &filter).is_none()
+    }
+
+    /// Returns true if nothing occludes the segment between the origins of the two entities,
+    /// ignoring their own meshes. Returns [None] if either has no [GlobalTransform].
+    pub fn entities_line_of_sight(
+        &self,
+        from: Entity,
+        to: Entity,
+        filter: &RayCastFilter,
+    ) -> Option<bool> {
+        let from_translation = self.transform(from)?.translation;
+        let to_translation = self.transform(to)?.translation;
+        Some(self.line_of_sight(from_translation, to_translation, &[from, to], filter))
+    }
+
+    /// Returns the [GlobalTransform] of any entity.
+    pub fn transform(&self, entity: Entity) -> Option<&GlobalTransform> {
+        self.transform_query.get(entity).ok()
+    }
+
+    /// Returns the entity and all of its descendants, e.g. to exclude every mesh of a ship.
+    pub fn descendants(&self, entity: Entity) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.push_descendants(entity, &mut entities);
+        entities
+    }
+
+    fn push_descendants(&self, entity: Entity, entities: &mut Vec<Entity>) {
+        entities.push(entity);
+        if let Ok(children) = self.children_query.get(entity) {
+            for child in children.iter() {
+                self.push_descendants(*child, entities);
+            }
+        }
+    }
+
+    /// Returns the visible entities passing the filter whose bounding box passes the test.
+    /// Deformed skinned meshes are always kept, as their bounding box is that of the bind pose.
+    fn culled_entities(
+        &self,
+        filter: &RayCastFilter,
+        intersects_aabb: impl Fn(&Aabb, &Mat4) -> Option<[f32; 2]>,
+    ) -> Vec<Entity> {
+        self.culling_query
+            .iter()
+            .filter_map(|(visibility, aab, transform, layers, skin, entity)| {
+                if !visibility.is_visible || !filter.can_hit(entity, layers) {
+                    None
+                } else if self.state.deform_meshes && skin.is_some() {
+                    Some(entity)
+                } else {
+                    aab.and_then(|x| intersects_aabb(x, &transform.compute_matrix()))
+                        .and_then(|[_, far]| if far >= 0.0 { Some(entity) } else { None })
+                }
+            })
+            .collect()
+    }
+
+    /// Runs the intersection test in parallel against the triangles of each culled entity and
+    /// sorts the results by distance.
+    fn intersect_meshes(
+        &self,
+        culled_entities: &[Entity],
+        intersect: impl Fn(&MeshTriangles, &Mat4) -> Option<Intersection> + Sync,
+    ) -> Vec<(Entity, Intersection)> {
+        let mut picks = self.par_map_meshes(culled_entities, |entity, triangles, mesh_to_world| {
+            intersect(triangles, mesh_to_world).map(|intersection| (entity, intersection))
+        });
+        sort_by_distance(&mut picks);
+        picks
+    }
+
+    /// Calls `f` in parallel with the triangles and transform of each culled entity, in the
+    /// animated pose if mesh deformation is enabled. Results keep the order of the entities.
+    fn par_map_meshes<R: Send + 'static>(
+        &self,
+        culled_entities: &[Entity],
+        f: impl Fn(Entity, &MeshTriangles, &Mat4) -> Option<R> + Sync,
+    ) -> Vec<R> {
+        par_filter_map(&self.task_pool, culled_entities, |entity| {
+            let (mesh_handle, transform, skin, morph_targets, entity) =
+                self.mesh_query.get(*entity).ok()?;
+            let mesh = self.meshes.get(mesh_handle)?;
+            let triangles = match MeshTriangles::from_mesh(mesh) {
+                Ok(triangles) => triangles,
+                Err(error) => {
+                    self.reported_meshes.report(mesh_handle, &error);
+                    return None;
+                }
+            };
+
+            let deformed_positions =
+                if self.state.deform_meshes && (skin.is_some() || morph_targets.is_some()) {
+                    self.deformed_positions(mesh, entity, skin, morph_targets)
+                } else {
+                    None
+                };
+            match &deformed_positions {
+                // Skinned positions are already in world space
+                Some(positions) if skin.is_some() => f(
+                    entity,
+                    &triangles.with_positions(positions),
+                    &Mat4::IDENTITY,
+                ),
+                Some(positions) => f(
+                    entity,
+                    &triangles.with_positions(positions),
+                    &transform.compute_matrix(),
+                ),
+                None => f(entity, &triangles, &transform.compute_matrix()),
+            }
+        })
+    }
+
+    /// Animated positions of the mesh, computed at most once per frame for each entity.
+    fn deformed_positions(
+        &self,
+        mesh: &Mesh,
+        entity: Entity,
+        skin: Option<&SkinnedMesh>,
+        morph_targets: Option<&RayCastMorphTargets>,
+    ) -> Option<Arc<Vec<[f32; 3]>>> {
+        self.deformed_meshes.get_or_insert_with(entity, || {
+            let joint_matrices = match skin {
+                Some(skin) => Some(skin_joint_matrices(
+                    skin,
+                    self.inverse_bindposes.as_deref()?,
+                    &self.transform_query,
+                )?),
+                None => None,
+            };
+            deform_positions(mesh, morph_targets, joint_matrices.as_deref())
+        })
+    }
+}
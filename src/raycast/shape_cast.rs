@@ -0,0 +1,575 @@
+use std::f32::EPSILON;
+
+use bevy::{math::Vec3A, prelude::*, render::primitives::Aabb};
+
+use super::{
+    error::RaycastError,
+    primitives::{barycentric_coords, Backfaces, Intersection, Triangle},
+    ray::Ray3d,
+    triangles::MeshTriangles,
+};
+
+/// A convex shape that can be swept along a ray. With [Backfaces::Cull], triangles facing away
+/// from the direction of the sweep are skipped, as they are for rays.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CastShape {
+    Sphere { radius: f32 },
+    Cuboid { half_extents: Vec3, rotation: Quat },
+}
+
+impl CastShape {
+    /// Radius of a sphere centered on the shape that fully contains it.
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            CastShape::Sphere { radius } => *radius,
+            CastShape::Cuboid { half_extents, .. } => half_extents.length(),
+        }
+    }
+}
+
+/// Tests the ray against the world space bounding box of the mesh, grown by the shape's bounding
+/// radius, and returns the entry and exit distances.
+pub fn swept_aabb_intersection(
+    ray: &Ray3d,
+    aabb: &Aabb,
+    model_to_world: &Mat4,
+    shape: &CastShape,
+) -> Option<[f32; 2]> {
+    let center = model_to_world.transform_point3(Vec3::from(aabb.center));
+    let half_extents = Vec3::from(aabb.half_extents);
+    let world_half_extents = model_to_world.x_axis.truncate().abs() * half_extents.x
+        + model_to_world.y_axis.truncate().abs() * half_extents.y
+        + model_to_world.z_axis.truncate().abs() * half_extents.z
+        + Vec3::splat(shape.bounding_radius());
+    let world_aabb = Aabb::from_min_max(center - world_half_extents, center + world_half_extents);
+    ray.intersects_aabb(&world_aabb, &Mat4::IDENTITY)
+}
+
+/// Sweeps the shape along the ray and returns the first contact with the mesh. The distance of
+/// the returned [Intersection] is the distance travelled by the shape before the contact.
+pub fn compute_shape_intersection(
+    mesh: &Mesh,
+    mesh_to_world: &Mat4,
+    ray: &Ray3d,
+    shape: &CastShape,
+    max_distance: f32,
+    backfaces: Backfaces,
+) -> Result<Option<Intersection>, RaycastError> {
+    let triangles = MeshTriangles::from_mesh(mesh)?;
+    Ok(compute_shape_triangles_intersection(
+        &triangles,
+        mesh_to_world,
+        ray,
+        shape,
+        max_distance,
+        backfaces,
+    ))
+}
+
+pub fn compute_shape_triangles_intersection(
+    triangles: &MeshTriangles,
+    mesh_to_world: &Mat4,
+    ray: &Ray3d,
+    shape: &CastShape,
+    max_distance: f32,
+    backfaces: Backfaces,
+) -> Option<Intersection> {
+    let mut min_distance = max_distance;
+    let mut pick_intersection = None;
+
+    for (triangle_index, vertices) in triangles.iter() {
+        let [v0, v1, v2] = triangles.triangle_positions(vertices);
+        let triangle = Triangle::from([
+            mesh_to_world.transform_point3a(v0),
+            mesh_to_world.transform_point3a(v1),
+            mesh_to_world.transform_point3a(v2),
+        ]);
+        if backfaces == Backfaces::Cull && is_back_facing(ray, &triangle) {
+            continue;
+        }
+        let intersection = match shape {
+            CastShape::Sphere { radius } => {
+                sphere_triangle_sweep(ray, *radius, &triangle, min_distance)
+            }
+            CastShape::Cuboid {
+                half_extents,
+                rotation,
+            } => cuboid_triangle_sweep(ray, *half_extents, *rotation, &triangle, min_distance),
+        };
+        if let Some(i) = intersection {
+            min_distance = i.distance();
+            let i = i.with_barycentric_coords(barycentric_coords(i.position.into(), &triangle));
+            pick_intersection = Some(triangles.interpolate(i, triangle_index, vertices));
+        }
+    }
+
+    pick_intersection
+}
+
+/// Sweeps a sphere centered on the ray origin along the ray, returning the first contact with the
+/// triangle within `max_distance`.
+pub fn sphere_triangle_sweep(
+    ray: &Ray3d,
+    radius: f32,
+    triangle: &Triangle,
+    max_distance: f32,
+) -> Option<Intersection> {
+    let mut normal = (triangle.v1 - triangle.v0).cross(triangle.v2 - triangle.v0);
+    if normal.length_squared() < EPSILON {
+        return None;
+    }
+    normal = normal.normalize();
+    let winding_normal = normal;
+
+    let mut plane_distance = (ray.origin - triangle.v0).dot(normal);
+    if plane_distance < 0.0 {
+        normal = -normal;
+        plane_distance = -plane_distance;
+    }
+
+    // Already touching the triangle at the start of the sweep
+    if plane_distance <= radius {
+        let closest = closest_point_on_triangle(ray.origin, triangle);
+        let offset = ray.origin - closest;
+        if offset.length_squared() <= radius * radius {
+            let contact_normal = if offset.length_squared() > EPSILON {
+                offset.normalize()
+            } else {
+                normal
+            };
+            return Some(Intersection::new(
+                closest.into(),
+                contact_normal.into(),
+                0.0,
+                Some(*triangle),
+            ));
+        }
+    }
+
+    // Contact with the face of the triangle
+    let approach_speed = -ray.direction.dot(normal);
+    if approach_speed > EPSILON && plane_distance > radius {
+        let distance = (plane_distance - radius) / approach_speed;
+        if distance <= max_distance {
+            let contact = ray.origin + ray.direction * distance - normal * radius;
+            if point_in_triangle(contact, triangle, winding_normal) {
+                return Some(Intersection::new(
+                    contact.into(),
+                    normal.into(),
+                    distance,
+                    Some(*triangle),
+                ));
+            }
+        }
+    }
+
+    // Contact with one of the vertices or edges of the triangle
+    let mut min_distance = max_distance;
+    let mut contact = None;
+    for vertex in [triangle.v0, triangle.v1, triangle.v2] {
+        if let Some(distance) = ray_sphere_distance(ray, vertex, radius) {
+            if distance <= min_distance {
+                min_distance = distance;
+                contact = Some(vertex);
+            }
+        }
+    }
+    for (start, end) in [
+        (triangle.v0, triangle.v1),
+        (triangle.v1, triangle.v2),
+        (triangle.v2, triangle.v0),
+    ] {
+        if let Some((distance, point)) = ray_capsule_side_distance(ray, start, end, radius) {
+            if distance <= min_distance {
+                min_distance = distance;
+                contact = Some(point);
+            }
+        }
+    }
+
+    contact.map(|contact| {
+        let center = ray.origin + ray.direction * min_distance;
+        Intersection::new(
+            contact.into(),
+            (center - contact).normalize().into(),
+            min_distance,
+            Some(*triangle),
+        )
+    })
+}
+
+/// Sweeps an oriented box centered on the ray origin along the ray, returning the first contact
+/// with the triangle within `max_distance`. Uses the separating axis theorem extended with the
+/// motion of the box along each axis.
+pub fn cuboid_triangle_sweep(
+    ray: &Ray3d,
+    half_extents: Vec3,
+    rotation: Quat,
+    triangle: &Triangle,
+    max_distance: f32,
+) -> Option<Intersection> {
+    let box_axes = [
+        Vec3A::from(rotation * Vec3::X),
+        Vec3A::from(rotation * Vec3::Y),
+        Vec3A::from(rotation * Vec3::Z),
+    ];
+    let half_extents = Vec3A::from(half_extents);
+    let edges = [
+        triangle.v1 - triangle.v0,
+        triangle.v2 - triangle.v1,
+        triangle.v0 - triangle.v2,
+    ];
+    let triangle_normal = edges[0].cross(edges[1]);
+    if triangle_normal.length_squared() < EPSILON {
+        return None;
+    }
+
+    let mut axes = vec![triangle_normal];
+    axes.extend(box_axes);
+    for box_axis in box_axes {
+        for edge in edges {
+            axes.push(box_axis.cross(edge));
+        }
+    }
+
+    let mut enter = f32::MIN;
+    let mut exit = f32::MAX;
+    let mut enter_axis = triangle_normal;
+    for axis in axes {
+        if axis.length_squared() < EPSILON {
+            continue;
+        }
+        let axis = axis.normalize();
+
+        let box_center = ray.origin.dot(axis);
+        let box_radius = half_extents.x * box_axes[0].dot(axis).abs()
+            + half_extents.y * box_axes[1].dot(axis).abs()
+            + half_extents.z * box_axes[2].dot(axis).abs();
+        let projections = [
+            triangle.v0.dot(axis),
+            triangle.v1.dot(axis),
+            triangle.v2.dot(axis),
+        ];
+        let triangle_min = projections[0].min(projections[1]).min(projections[2]);
+        let triangle_max = projections[0].max(projections[1]).max(projections[2]);
+        let speed = ray.direction.dot(axis);
+
+        if speed.abs() < EPSILON {
+            if box_center + box_radius < triangle_min || box_center - box_radius > triangle_max {
+                return None;
+            }
+            continue;
+        }
+
+        let t0 = (triangle_min - box_radius - box_center) / speed;
+        let t1 = (triangle_max + box_radius - box_center) / speed;
+        let (axis_enter, axis_exit) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+        if axis_enter > enter {
+            enter = axis_enter;
+            enter_axis = axis;
+        }
+        exit = exit.min(axis_exit);
+        if enter > exit || exit < 0.0 || enter > max_distance {
+            return None;
+        }
+    }
+
+    let distance = enter.max(0.0);
+    let center = ray.origin + ray.direction * distance;
+    let contact = closest_point_on_triangle(center, triangle);
+    let normal = if enter >= 0.0 {
+        if enter_axis.dot(ray.direction) > 0.0 {
+            -enter_axis
+        } else {
+            enter_axis
+        }
+    } else {
+        let normal = triangle_normal.normalize();
+        if normal.dot(center - triangle.v0) < 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    };
+
+    Some(Intersection::new(
+        contact.into(),
+        normal.into(),
+        distance,
+        Some(*triangle),
+    ))
+}
+
+/// Whether the winding of the triangle faces away from the direction of the ray, or is edge-on.
+fn is_back_facing(ray: &Ray3d, triangle: &Triangle) -> bool {
+    let normal = (triangle.v1 - triangle.v0).cross(triangle.v2 - triangle.v0);
+    ray.direction.dot(normal) > -EPSILON
+}
+
+/// Distance along the ray at which it enters a sphere, if it does so in front of its origin.
+fn ray_sphere_distance(ray: &Ray3d, center: Vec3A, radius: f32) -> Option<f32> {
+    let offset = ray.origin - center;
+    let b = offset.dot(ray.direction);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    if distance >= 0.0 {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/// Distance along the ray at which it enters the cylindrical side of the capsule around the
+/// segment, together with the closest point of the segment at that moment.
+fn ray_capsule_side_distance(
+    ray: &Ray3d,
+    start: Vec3A,
+    end: Vec3A,
+    radius: f32,
+) -> Option<(f32, Vec3A)> {
+    let segment = end - start;
+    let segment_length_squared = segment.length_squared();
+    if segment_length_squared < EPSILON {
+        return None;
+    }
+    let offset = ray.origin - start;
+
+    // Remove the components parallel to the segment, leaving a 2D circle intersection
+    let direction = ray.direction - segment * (ray.direction.dot(segment) / segment_length_squared);
+    let offset_perpendicular = offset - segment * (offset.dot(segment) / segment_length_squared);
+
+    let a = direction.length_squared();
+    if a < EPSILON {
+        return None;
+    }
+    let b = offset_perpendicular.dot(direction);
+    let c = offset_perpendicular.length_squared() - radius * radius;
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let distance = (-b - discriminant.sqrt()) / a;
+    if distance < 0.0 {
+        return None;
+    }
+
+    let center = ray.origin + ray.direction * distance;
+    let along = (center - start).dot(segment) / segment_length_squared;
+    if (0.0..=1.0).contains(&along) {
+        Some((distance, start + segment * along))
+    } else {
+        None
+    }
+}
+
+fn point_in_triangle(point: Vec3A, triangle: &Triangle, normal: Vec3A) -> bool {
+    (triangle.v1 - triangle.v0)
+        .cross(point - triangle.v0)
+        .dot(normal)
+        >= 0.0
+        && (triangle.v2 - triangle.v1)
+            .cross(point - triangle.v1)
+            .dot(normal)
+            >= 0.0
+        && (triangle.v0 - triangle.v2)
+            .cross(point - triangle.v2)
+            .dot(normal)
+            >= 0.0
+}
+
+/// Closest point on a triangle to a point, from Real-Time Collision Detection (Ericson, 5.1.5).
+pub fn closest_point_on_triangle(point: Vec3A, triangle: &Triangle) -> Vec3A {
+    let (a, b, c) = (triangle.v0, triangle.v1, triangle.v2);
+    let ab = b - a;
+    let ac = c - a;
+    let ap = point - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = point - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = point - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denominator = 1.0 / (va + vb + vc);
+    let v = vb * denominator;
+    let w = vc * denominator;
+    a + ab * v + ac * w
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::{mesh::Indices, render_resource::PrimitiveTopology};
+
+    use super::*;
+
+    /// Corners of a right triangle in the XY plane, facing +Z.
+    fn corners() -> [Vec3; 3] {
+        [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+        ]
+    }
+
+    fn triangle() -> Triangle {
+        Triangle::from(corners().map(Vec3A::from))
+    }
+
+    fn cube(half_extent: f32) -> (Vec3, Quat) {
+        (Vec3::splat(half_extent), Quat::IDENTITY)
+    }
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-4),
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn sphere_grazes_edge() {
+        // The sphere passes just outside the edge from the first to the second vertex
+        let ray = Ray3d::new(Vec3::new(1.0, -0.3, 5.0), -Vec3::Z);
+        let hit = sphere_triangle_sweep(&ray, 0.5, &triangle(), f32::MAX).unwrap();
+        assert!((hit.distance - 4.6).abs() < 1e-4);
+        assert_near(hit.position, Vec3::new(1.0, 0.0, 0.0));
+        assert_near(hit.normal, Vec3::new(0.0, -0.6, 0.8));
+
+        let ray = Ray3d::new(Vec3::new(1.0, -0.501, 5.0), -Vec3::Z);
+        assert!(sphere_triangle_sweep(&ray, 0.5, &triangle(), f32::MAX).is_none());
+    }
+
+    #[test]
+    fn sphere_starts_in_contact() {
+        let ray = Ray3d::new(Vec3::new(0.5, 0.5, 0.2), Vec3::X);
+        let hit = sphere_triangle_sweep(&ray, 0.5, &triangle(), f32::MAX).unwrap();
+        assert_eq!(hit.distance, 0.0);
+        assert_near(hit.position, Vec3::new(0.5, 0.5, 0.0));
+        assert_near(hit.normal, Vec3::Z);
+    }
+
+    #[test]
+    fn cuboid_hits_face() {
+        let (half_extents, rotation) = cube(0.25);
+        let ray = Ray3d::new(Vec3::new(0.5, 0.5, 5.0), -Vec3::Z);
+        let hit =
+            cuboid_triangle_sweep(&ray, half_extents, rotation, &triangle(), f32::MAX).unwrap();
+        assert!((hit.distance - 4.75).abs() < 1e-4);
+        assert_near(hit.position, Vec3::new(0.5, 0.5, 0.0));
+        assert_near(hit.normal, Vec3::Z);
+    }
+
+    #[test]
+    fn cuboid_hits_edge() {
+        // Slides along the plane of the triangle into the edge from the third to the first vertex
+        let (half_extents, rotation) = cube(0.25);
+        let ray = Ray3d::new(Vec3::new(-1.0, 0.5, 0.0), Vec3::X);
+        let hit =
+            cuboid_triangle_sweep(&ray, half_extents, rotation, &triangle(), f32::MAX).unwrap();
+        assert!((hit.distance - 0.75).abs() < 1e-4);
+        assert_near(hit.position, Vec3::new(0.0, 0.5, 0.0));
+        assert_near(hit.normal, -Vec3::X);
+    }
+
+    #[test]
+    fn cuboid_hits_vertex() {
+        // Slides along the plane of the triangle into its second vertex
+        let (half_extents, rotation) = cube(0.25);
+        let ray = Ray3d::new(Vec3::new(3.0, 0.0, 0.0), -Vec3::X);
+        let hit =
+            cuboid_triangle_sweep(&ray, half_extents, rotation, &triangle(), f32::MAX).unwrap();
+        assert!((hit.distance - 0.75).abs() < 1e-4);
+        assert_near(hit.position, corners()[1]);
+        assert_near(hit.normal, Vec3::X);
+
+        // Passing beside the vertex misses
+        let ray = Ray3d::new(Vec3::new(3.0, -0.3, 0.0), -Vec3::X);
+        assert!(
+            cuboid_triangle_sweep(&ray, half_extents, rotation, &triangle(), f32::MAX).is_none()
+        );
+    }
+
+    #[test]
+    fn backface_culling() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            corners()
+                .iter()
+                .map(|corner| corner.to_array())
+                .collect::<Vec<_>>(),
+        );
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 2])));
+
+        // Approaching from behind the triangle
+        let ray = Ray3d::new(Vec3::new(0.5, 0.5, -5.0), Vec3::Z);
+        let (half_extents, rotation) = cube(0.25);
+        let shapes = [
+            (CastShape::Sphere { radius: 0.5 }, 4.5),
+            (
+                CastShape::Cuboid {
+                    half_extents,
+                    rotation,
+                },
+                4.75,
+            ),
+        ];
+        for (shape, distance) in shapes {
+            let culled = compute_shape_intersection(
+                &mesh,
+                &Mat4::IDENTITY,
+                &ray,
+                &shape,
+                f32::MAX,
+                Backfaces::Cull,
+            )
+            .unwrap();
+            assert!(culled.is_none(), "{shape:?} hit a back face");
+
+            let hit = compute_shape_intersection(
+                &mesh,
+                &Mat4::IDENTITY,
+                &ray,
+                &shape,
+                f32::MAX,
+                Backfaces::Include,
+            )
+            .unwrap()
+            .unwrap();
+            assert!((hit.distance - distance).abs() < 1e-4, "{shape:?}");
+            assert_near(hit.normal, -Vec3::Z);
+        }
+    }
+}
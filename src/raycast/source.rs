@@ -1,13 +1,28 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use std::marker::PhantomData;
 
-use super::{primitives::Intersection, ray::Ray3d, RayCastMethod};
+use super::{
+    primitives::{Backfaces, IntersectionAlgorithm},
+    ray::Ray3d,
+    RayCastFilter, RayCastHitMode, RayCastHits, RayCastLayers, RayCastMethod,
+};
 
 #[derive(Component)]
 pub struct RayCastSource<T> {
     pub cast_method: RayCastMethod,
     pub ray: Option<Ray3d>,
-    pub intersections: Vec<(Entity, Intersection)>,
+    /// Whether triangles facing away from the ray are ignored.
+    pub backfaces: Backfaces,
+    /// Ray-triangle test to use, [IntersectionAlgorithm::Watertight] to never slip through the
+    /// edges shared by adjacent triangles.
+    pub algorithm: IntersectionAlgorithm,
+    /// Intersections further away than this world space distance are discarded.
+    pub max_distance: f32,
+    pub hit_mode: RayCastHitMode,
+    /// Only meshes sharing at least one layer with this mask can be hit.
+    pub mask: RayCastLayers,
+    /// Entities this source never hits, e.g. the mesh of the ship it is attached to.
+    pub exclude: Vec<Entity>,
     _marker: PhantomData<T>,
 }
 
@@ -16,7 +31,12 @@ impl<T> Default for RayCastSource<T> {
         RayCastSource {
             cast_method: RayCastMethod::Screenspace(Vec2::ZERO),
             ray: None,
-            intersections: Vec::new(),
+            backfaces: Backfaces::default(),
+            algorithm: IntersectionAlgorithm::default(),
+            max_distance: f32::MAX,
+            hit_mode: RayCastHitMode::default(),
+            mask: RayCastLayers::ALL,
+            exclude: Vec::new(),
             _marker: PhantomData::default(),
         }
     }
@@ -36,4 +56,92 @@ impl<T> RayCastSource<T> {
             ..Default::default()
         }
     }
+
+    pub fn with_backfaces(mut self, backfaces: Backfaces) -> Self {
+        self.backfaces = backfaces;
+        self
+    }
+
+    pub fn with_algorithm(mut self, algorithm: IntersectionAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_hit_mode(mut self, hit_mode: RayCastHitMode) -> Self {
+        self.hit_mode = hit_mode;
+        self
+    }
+
+    pub fn with_mask(mut self, mask: RayCastLayers) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn with_exclude(mut self, exclude: Vec<Entity>) -> Self {
+        self.exclude = exclude;
+        self
+    }
+
+    /// Returns the [RayCastFilter] described by this source's settings.
+    pub fn filter(&self) -> RayCastFilter {
+        RayCastFilter {
+            backfaces: self.backfaces,
+            algorithm: self.algorithm,
+            max_distance: self.max_distance,
+            mask: self.mask,
+            exclude: &self.exclude,
+        }
+    }
+
+    /// Returns true if a mesh entity with the given layers passes this source's filters.
+    pub fn can_hit(&self, entity: Entity, layers: Option<&RayCastLayers>) -> bool {
+        self.filter().can_hit(entity, layers)
+    }
+}
+
+/// Looks up the [RayCastSource] entities of the set `T` and their [RayCastHits], so systems can
+/// pick the source they act on instead of assuming there is only one.
+#[derive(SystemParam)]
+pub struct RayCastSources<'w, 's, T: 'static + Send + Sync> {
+    query: Query<
+        'w,
+        's,
+        (
+            &'static RayCastSource<T>,
+            Option<&'static RayCastHits<T>>,
+            Entity,
+        ),
+    >,
+}
+
+impl<'w, 's, T: 'static + Send + Sync> RayCastSources<'w, 's, T> {
+    /// Returns the source of the entity and its hits, which are missing until its first update.
+    pub fn get(&self, entity: Entity) -> Option<(&RayCastSource<T>, Option<&RayCastHits<T>>)> {
+        self.query
+            .get(entity)
+            .ok()
+            .map(|(source, hits, _)| (source, hits))
+    }
+
+    pub fn source(&self, entity: Entity) -> Option<&RayCastSource<T>> {
+        self.get(entity).map(|(source, _)| source)
+    }
+
+    pub fn hits(&self, entity: Entity) -> Option<&RayCastHits<T>> {
+        self.get(entity).and_then(|(_, hits)| hits)
+    }
+
+    /// Iterates over every source entity.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (Entity, &RayCastSource<T>, Option<&RayCastHits<T>>)> {
+        self.query
+            .iter()
+            .map(|(source, hits, entity)| (entity, source, hits))
+    }
 }
@@ -5,7 +5,12 @@ use std::marker::PhantomData;
 pub struct DefaultPluginState<T> {
     pub compute_ray: ShouldRun,
     pub update_raycast: ShouldRun,
+    /// Draws rays and intersections with [bevy_prototype_debug_lines] when set and
+    /// [RaycastDebugPlugin](super::RaycastDebugPlugin) is added.
     pub update_debug_cursor: bool,
+    /// Applies skinning and [RayCastMorphTargets](super::deform::RayCastMorphTargets) on the CPU
+    /// so animated meshes are hit where they are drawn.
+    pub deform_meshes: bool,
     _marker: PhantomData<T>,
 }
 
@@ -15,6 +20,7 @@ impl<T> Default for DefaultPluginState<T> {
             compute_ray: ShouldRun::Yes,
             update_raycast: ShouldRun::Yes,
             update_debug_cursor: false,
+            deform_meshes: false,
             _marker: PhantomData::<T>::default(),
         }
     }
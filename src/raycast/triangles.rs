@@ -0,0 +1,361 @@
+use bevy::{
+    math::Vec3A,
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
+};
+
+use super::{error::RaycastError, primitives::Intersection};
+
+/// Vertex colors, either as floats or packed as little endian RGBA8.
+#[derive(Clone, Copy)]
+pub enum VertexColors<'a> {
+    Float(&'a [[f32; 4]]),
+    Packed(&'a [u32]),
+}
+
+impl<'a> VertexColors<'a> {
+    pub fn get(&self, vertex: usize) -> Vec4 {
+        match self {
+            VertexColors::Float(colors) => Vec4::from(colors[vertex]),
+            VertexColors::Packed(colors) => {
+                let [r, g, b, a] = colors[vertex].to_le_bytes();
+                Vec4::new(r as f32, g as f32, b as f32, a as f32) / 255.0
+            }
+        }
+    }
+}
+
+/// Read-only view over the triangles of a [Mesh], for indexed and non-indexed triangle lists and
+/// triangle strips.
+pub struct MeshTriangles<'a> {
+    pub positions: &'a [[f32; 3]],
+    pub normals: Option<&'a [[f32; 3]]>,
+    pub uvs: Option<&'a [[f32; 2]]>,
+    pub colors: Option<VertexColors<'a>>,
+    indices: Option<&'a Indices>,
+    topology: PrimitiveTopology,
+    index_count: usize,
+    /// Positions of the restart indices of a strip, in increasing order.
+    restarts: Vec<usize>,
+}
+
+impl<'a> MeshTriangles<'a> {
+    pub fn from_mesh(mesh: &'a Mesh) -> Result<Self, RaycastError> {
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(positions)) => positions.as_slice(),
+            Some(_) => return Err(RaycastError::UnsupportedPositionFormat),
+            None => return Err(RaycastError::MissingPositions),
+        };
+
+        let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) if normals.len() == positions.len() => {
+                Some(normals.as_slice())
+            }
+            _ => None,
+        };
+
+        let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) if uvs.len() == positions.len() => {
+                Some(uvs.as_slice())
+            }
+            _ => None,
+        };
+
+        let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(colors)) if colors.len() == positions.len() => {
+                Some(VertexColors::Float(colors))
+            }
+            Some(VertexAttributeValues::Uint32(colors)) if colors.len() == positions.len() => {
+                Some(VertexColors::Packed(colors))
+            }
+            _ => None,
+        };
+
+        let topology = mesh.primitive_topology();
+        let indices = mesh.indices();
+        let index_count = indices.map_or(positions.len(), Indices::len);
+
+        match topology {
+            PrimitiveTopology::TriangleList if index_count % 3 != 0 => {
+                return Err(RaycastError::InvalidIndexCount(index_count))
+            }
+            PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip => {}
+            topology => return Err(RaycastError::UnsupportedTopology(topology)),
+        }
+
+        // Strips may use the maximum index value to restart the strip
+        let is_strip = topology == PrimitiveTopology::TriangleStrip;
+        let (max_index, restarts) = match indices {
+            Some(Indices::U16(indices)) => scan_indices(indices, is_strip, u16::MAX),
+            Some(Indices::U32(indices)) => scan_indices(indices, is_strip, u32::MAX),
+            None => (None, Vec::new()),
+        };
+        if let Some(index) = max_index.filter(|&index| index >= positions.len()) {
+            return Err(RaycastError::IndexOutOfBounds {
+                index,
+                vertex_count: positions.len(),
+            });
+        }
+
+        Ok(MeshTriangles {
+            positions,
+            normals,
+            uvs,
+            colors,
+            indices,
+            topology,
+            index_count,
+            restarts,
+        })
+    }
+
+    /// Replaces the vertex positions, e.g. with animated ones of the same length. Vertex normals
+    /// are dropped as they no longer match, so face normals are used instead.
+    pub fn with_positions(mut self, positions: &'a [[f32; 3]]) -> Self {
+        debug_assert_eq!(self.positions.len(), positions.len());
+        self.positions = positions;
+        self.normals = None;
+        self
+    }
+
+    /// Number of triangles, including the degenerate ones of a strip.
+    pub fn len(&self) -> usize {
+        match self.topology {
+            PrimitiveTopology::TriangleStrip => self.index_count.saturating_sub(2),
+            _ => self.index_count / 3,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Index and vertex indices of every triangle, skipping those interrupted by a strip restart
+    /// index.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, [usize; 3])> + '_ {
+        (0..self.len()).filter_map(move |triangle| {
+            self.triangle_indices(triangle)
+                .map(|vertices| (triangle, vertices))
+        })
+    }
+
+    /// Vertex indices of the given triangle. Odd triangles of a strip, counted from the last
+    /// restart, are flipped to keep the winding order consistent.
+    pub fn triangle_indices(&self, triangle: usize) -> Option<[usize; 3]> {
+        let indices = match self.topology {
+            PrimitiveTopology::TriangleStrip
+                if (triangle - self.strip_start(triangle)) % 2 == 1 =>
+            {
+                [triangle + 1, triangle, triangle + 2]
+            }
+            PrimitiveTopology::TriangleStrip => [triangle, triangle + 1, triangle + 2],
+            _ => [triangle * 3, triangle * 3 + 1, triangle * 3 + 2],
+        };
+        let vertices = [
+            self.vertex_index(indices[0])?,
+            self.vertex_index(indices[1])?,
+            self.vertex_index(indices[2])?,
+        ];
+        Some(vertices)
+    }
+
+    pub fn triangle_positions(&self, vertices: [usize; 3]) -> [Vec3A; 3] {
+        [
+            Vec3A::from(self.positions[vertices[0]]),
+            Vec3A::from(self.positions[vertices[1]]),
+            Vec3A::from(self.positions[vertices[2]]),
+        ]
+    }
+
+    pub fn triangle_normals(&self, vertices: [usize; 3]) -> Option<[Vec3A; 3]> {
+        self.normals.map(|normals| {
+            [
+                Vec3A::from(normals[vertices[0]]),
+                Vec3A::from(normals[vertices[1]]),
+                Vec3A::from(normals[vertices[2]]),
+            ]
+        })
+    }
+
+    /// Fills in the triangle index and the vertex attributes interpolated with the barycentric
+    /// coordinates of the intersection.
+    pub fn interpolate(
+        &self,
+        intersection: Intersection,
+        triangle: usize,
+        vertices: [usize; 3],
+    ) -> Intersection {
+        let [v0, v1, v2] = vertices;
+        Intersection {
+            triangle_index: Some(triangle),
+            uv: self.uvs.map(|uvs| {
+                intersection.interpolate([
+                    Vec2::from(uvs[v0]),
+                    Vec2::from(uvs[v1]),
+                    Vec2::from(uvs[v2]),
+                ])
+            }),
+            color: self.colors.map(|colors| {
+                let color =
+                    intersection.interpolate([colors.get(v0), colors.get(v1), colors.get(v2)]);
+                Color::rgba_linear(color.x, color.y, color.z, color.w)
+            }),
+            ..intersection
+        }
+    }
+
+    /// Position in the index buffer of the first index of the strip the triangle belongs to.
+    fn strip_start(&self, triangle: usize) -> usize {
+        let previous_restarts = self.restarts.partition_point(|&restart| restart < triangle);
+        match previous_restarts {
+            0 => 0,
+            count => self.restarts[count - 1] + 1,
+        }
+    }
+
+    /// Resolves an entry of the index buffer, returning [None] for a strip restart index.
+    fn vertex_index(&self, index: usize) -> Option<usize> {
+        let is_strip = self.topology == PrimitiveTopology::TriangleStrip;
+        match self.indices {
+            Some(Indices::U16(indices)) => match indices[index] {
+                u16::MAX if is_strip => None,
+                vertex => Some(vertex as usize),
+            },
+            Some(Indices::U32(indices)) => match indices[index] {
+                u32::MAX if is_strip => None,
+                vertex => Some(vertex as usize),
+            },
+            None => Some(index),
+        }
+    }
+}
+
+/// Returns the highest vertex index, and the positions of the restart indices of a strip.
+fn scan_indices<I>(indices: &[I], is_strip: bool, restart: I) -> (Option<usize>, Vec<usize>)
+where
+    I: Copy + Ord + Into<u64>,
+{
+    let mut max_index = None;
+    let mut restarts = Vec::new();
+    for (position, &index) in indices.iter().enumerate() {
+        if is_strip && index == restart {
+            restarts.push(position);
+        } else {
+            max_index = max_index.max(Some(index.into() as usize));
+        }
+    }
+    (max_index, restarts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh(topology: PrimitiveTopology, vertex_count: usize, indices: Option<Indices>) -> Mesh {
+        // Zigzag in the XY plane, so every triangle of a strip faces +Z once unflipped
+        let positions: Vec<[f32; 3]> = (0..vertex_count)
+            .map(|vertex| [(vertex / 2) as f32, (vertex % 2) as f32, 0.0])
+            .collect();
+        let mut mesh = Mesh::new(topology);
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_indices(indices);
+        mesh
+    }
+
+    fn triangles(mesh: &Mesh) -> Vec<[usize; 3]> {
+        let triangles = MeshTriangles::from_mesh(mesh).unwrap();
+        triangles.iter().map(|(_, vertices)| vertices).collect()
+    }
+
+    fn facing(mesh: &Mesh, vertices: [usize; 3]) -> f32 {
+        let triangles = MeshTriangles::from_mesh(mesh).unwrap();
+        let [v0, v1, v2] = triangles.triangle_positions(vertices);
+        (v1 - v0).cross(v2 - v0).z
+    }
+
+    #[test]
+    fn triangle_list() {
+        let mesh = mesh(PrimitiveTopology::TriangleList, 6, None);
+        assert_eq!(triangles(&mesh), vec![[0, 1, 2], [3, 4, 5]]);
+
+        let mesh = mesh_with_indices(PrimitiveTopology::TriangleList, 4, vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(triangles(&mesh), vec![[0, 1, 2], [2, 1, 3]]);
+    }
+
+    #[test]
+    fn triangle_strip() {
+        let mesh = mesh(PrimitiveTopology::TriangleStrip, 5, None);
+        let strip = triangles(&mesh);
+        assert_eq!(strip, vec![[0, 1, 2], [2, 1, 3], [2, 3, 4]]);
+        let first_facing = facing(&mesh, strip[0]);
+        for vertices in strip {
+            assert_eq!(facing(&mesh, vertices).signum(), first_facing.signum());
+        }
+    }
+
+    #[test]
+    fn triangle_strip_restart_at_odd_offset() {
+        // The second strip starts at index 3, so its first triangle must not be flipped
+        let mesh = mesh(
+            PrimitiveTopology::TriangleStrip,
+            7,
+            Some(Indices::U16(vec![0, 1, u16::MAX, 2, 3, 4, 5, 6])),
+        );
+        let strip = triangles(&mesh);
+        assert_eq!(strip, vec![[2, 3, 4], [4, 3, 5], [4, 5, 6]]);
+        let first_facing = facing(&mesh, strip[0]);
+        for vertices in strip {
+            assert_eq!(facing(&mesh, vertices).signum(), first_facing.signum());
+        }
+
+        let mesh = mesh_with_indices(
+            PrimitiveTopology::TriangleStrip,
+            5,
+            vec![0, 1, 2, u32::MAX, 2, 3, 4],
+        );
+        assert_eq!(triangles(&mesh), vec![[0, 1, 2], [2, 3, 4]]);
+    }
+
+    #[test]
+    fn index_out_of_bounds() {
+        let mesh = mesh_with_indices(PrimitiveTopology::TriangleList, 3, vec![0, 1, 5]);
+        assert_eq!(
+            MeshTriangles::from_mesh(&mesh).err(),
+            Some(RaycastError::IndexOutOfBounds {
+                index: 5,
+                vertex_count: 3
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_index_count() {
+        let mesh = mesh_with_indices(PrimitiveTopology::TriangleList, 3, vec![0, 1, 2, 0]);
+        assert_eq!(
+            MeshTriangles::from_mesh(&mesh).err(),
+            Some(RaycastError::InvalidIndexCount(4))
+        );
+    }
+
+    #[test]
+    fn unsupported_topology() {
+        let mesh = mesh(PrimitiveTopology::LineList, 4, None);
+        assert_eq!(
+            MeshTriangles::from_mesh(&mesh).err(),
+            Some(RaycastError::UnsupportedTopology(
+                PrimitiveTopology::LineList
+            ))
+        );
+    }
+
+    fn mesh_with_indices(
+        topology: PrimitiveTopology,
+        vertex_count: usize,
+        indices: Vec<u32>,
+    ) -> Mesh {
+        mesh(topology, vertex_count, Some(Indices::U32(indices)))
+    }
+}
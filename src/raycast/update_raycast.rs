@@ -1,86 +1,38 @@
-use std::{
-    collections::BTreeMap,
-    f32::EPSILON,
-    sync::{Arc, Mutex},
-};
+use std::f32::EPSILON;
 
-use bevy::{
-    core::FloatOrd,
-    math::Vec3A,
-    prelude::*,
-    render::mesh::{Indices, VertexAttributeValues},
-    tasks::ComputeTaskPool,
-};
+use bevy::{math::Vec3A, prelude::*};
 
 use super::{
+    error::RaycastError,
     event::HoverEvent,
-    primitives::{Backfaces, Intersection, IntoUsize, RayHit, Triangle, TriangleTrait},
+    primitives::{Backfaces, Intersection, IntersectionAlgorithm, RayHit, Triangle, TriangleTrait},
     ray::Ray3d,
-    RayCastMesh, RayCastSource,
+    triangles::MeshTriangles,
+    RayCastHitMode, RayCastHits, RayCastSource, RayCaster,
 };
 
-#[allow(clippy::type_complexity)]
 pub fn update_raycast<T: 'static + Send + Sync>(
-    meshes: Res<Assets<Mesh>>,
-    task_pool: Res<ComputeTaskPool>,
+    mut commands: Commands,
+    ray_caster: RayCaster<T>,
     mut hover_events: EventWriter<HoverEvent>,
-    mut source_query: Query<&mut RayCastSource<T>>,
-    culling_query: Query<
-        (
-            &Visibility,
-            Option<&bevy::render::primitives::Aabb>,
-            &GlobalTransform,
-            Entity,
-        ),
-        With<RayCastMesh<T>>,
-    >,
-    mesh_query: Query<(&Handle<Mesh>, &GlobalTransform, Entity), With<RayCastMesh<T>>>,
+    mut source_query: Query<(&RayCastSource<T>, Option<&mut RayCastHits<T>>, Entity)>,
 ) {
-    for mut source in source_query.iter_mut() {
+    for (source, hits, source_entity) in source_query.iter_mut() {
         if let Some(ray) = source.ray {
-            source.intersections.clear();
-
-            let culled_entities: Vec<Entity> = culling_query
-                .iter()
-                .filter_map(|(visibility, aab, transform, entity)| {
-                    if visibility.is_visible {
-                        aab.and_then(|x| ray.intersects_aabb(x, &transform.compute_matrix()))
-                            .and_then(|[_, far]| if far >= 0.0 { Some(entity) } else { None })
-                    } else {
-                        return None;
-                    }
-                })
-                .collect();
-
-            if !culled_entities.is_empty() {
-                let picks = Arc::new(Mutex::new(BTreeMap::new()));
-                mesh_query.par_for_each(&task_pool, 32, |(mesh_handle, transform, entity)| {
-                    if culled_entities.contains(&entity) {
-                        meshes
-                            .get(mesh_handle)
-                            .and_then(|x| {
-                                compute_intersection(x, &transform.compute_matrix(), &ray)
-                            })
-                            .and_then(|intersection| {
-                                picks.lock().unwrap().insert(
-                                    FloatOrd(intersection.distance()),
-                                    (entity, intersection),
-                                )
-                            });
-                    }
-                });
-                let picks = Arc::try_unwrap(picks)
-                    .unwrap()
-                    .into_inner()
-                    .unwrap()
-                    .into_values()
-                    .collect();
-                source.intersections = picks;
-                for (entity, _) in source.intersections.iter() {
-                    hover_events.send(HoverEvent::JustEntered(*entity));
+            let mut picks = ray_caster.cast_all(&ray, &source.filter());
+            if source.hit_mode == RayCastHitMode::First {
+                picks.truncate(1);
+            }
+            for (entity, _) in picks.iter() {
+                hover_events.send(HoverEvent::JustEntered(*entity));
+            }
+            match hits {
+                Some(mut hits) => hits.intersections = picks,
+                None => {
+                    let mut hits = RayCastHits::<T>::default();
+                    hits.intersections = picks;
+                    commands.entity(source_entity).insert(hits);
                 }
-            } else {
-                source.intersections = Vec::new();
             }
         }
     }
@@ -90,100 +42,48 @@ pub fn compute_intersection(
     mesh: &Mesh,
     mesh_to_world: &Mat4,
     ray: &Ray3d,
-) -> Option<Intersection> {
-    let positions = match mesh
-        .attribute(Mesh::ATTRIBUTE_POSITION)
-        .expect("Mesh does not contain vertex positions")
-    {
-        VertexAttributeValues::Float32x3(positions) => positions,
-        _ => panic!("Unexpected types in {}", Mesh::ATTRIBUTE_POSITION),
-    };
-
-    let normals: Option<&[[f32; 3]]> =
-        if let Some(normal_values) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
-            match &normal_values {
-                VertexAttributeValues::Float32x3(normals) => Some(normals),
-                _ => None,
-            }
-        } else {
-            None
-        };
-
-    if let Some(indices) = &mesh.indices() {
-        // Iterate over the list of pick rays that belong to the same group as this mesh
-        match indices {
-            Indices::U16(vertex_indices) => {
-                _compute_intersection(mesh_to_world, positions, normals, ray, Some(vertex_indices))
-            }
-            Indices::U32(vertex_indices) => {
-                _compute_intersection(mesh_to_world, positions, normals, ray, Some(vertex_indices))
-            }
-        }
-    } else {
-        None
-    }
+    backfaces: Backfaces,
+    algorithm: IntersectionAlgorithm,
+    max_distance: f32,
+) -> Result<Option<Intersection>, RaycastError> {
+    let triangles = MeshTriangles::from_mesh(mesh)?;
+    Ok(compute_triangles_intersection(
+        mesh_to_world,
+        &triangles,
+        ray,
+        backfaces,
+        algorithm,
+        max_distance,
+    ))
 }
 
-fn _compute_intersection(
+pub fn compute_triangles_intersection(
     mesh_to_world: &Mat4,
-    vertex_positions: &[[f32; 3]],
-    vertex_normals: Option<&[[f32; 3]]>,
+    triangles: &MeshTriangles,
     pick_ray: &Ray3d,
-    indices: Option<&Vec<impl IntoUsize>>,
+    backfaces: Backfaces,
+    algorithm: IntersectionAlgorithm,
+    max_distance: f32,
 ) -> Option<Intersection> {
     let mut min_pick_distance = f32::MAX;
     let mut pick_intersection = None;
 
-    let world_to_mesh = mesh_to_world.inverse();
+    let mesh_space_ray = pick_ray.transform(&mesh_to_world.inverse());
 
-    let mesh_space_ray = Ray3d::new(
-        world_to_mesh.transform_point3(pick_ray.origin.into()),
-        world_to_mesh.transform_vector3(pick_ray.direction.into()),
-    );
-
-    if let Some(indices) = indices {
-        // Make sure this chunk has 3 vertices to avoid a panic.
-        if indices.len() % 3 != 0 {
-            warn!("Index list not a multiple of 3");
-            return None;
-        }
-        // Now that we're in the vector of vertex indices, we want to look at the vertex
-        // positions for each triangle, so we'll take indices in chunks of three, where each
-        // chunk of three indices are references to the three vertices of a triangle.
-        for index in indices.chunks(3) {
-            let tri_vertex_positions = [
-                Vec3A::from(vertex_positions[index[0].into_usize()]),
-                Vec3A::from(vertex_positions[index[1].into_usize()]),
-                Vec3A::from(vertex_positions[index[2].into_usize()]),
-            ];
-            let tri_normals = vertex_normals.map(|normals| {
-                [
-                    Vec3A::from(normals[index[0].into_usize()]),
-                    Vec3A::from(normals[index[1].into_usize()]),
-                    Vec3A::from(normals[index[2].into_usize()]),
-                ]
-            });
-            let intersection = triangle_intersection(
-                tri_vertex_positions,
-                tri_normals,
-                min_pick_distance,
-                mesh_space_ray,
-            );
-            if let Some(i) = intersection {
-                pick_intersection = Some(Intersection::new(
-                    mesh_to_world.transform_point3(i.position),
-                    mesh_to_world.transform_vector3(i.normal),
-                    mesh_to_world
-                        .transform_vector3(mesh_space_ray.direction() * i.distance)
-                        .length(),
-                    i.triangle.map(|tri| {
-                        Triangle::from([
-                            mesh_to_world.transform_point3a(tri.v0),
-                            mesh_to_world.transform_point3a(tri.v1),
-                            mesh_to_world.transform_point3a(tri.v2),
-                        ])
-                    }),
-                ));
+    for (triangle, vertices) in triangles.iter() {
+        let intersection = triangle_intersection(
+            triangles.triangle_positions(vertices),
+            triangles.triangle_normals(vertices),
+            min_pick_distance,
+            mesh_space_ray,
+            backfaces,
+            algorithm,
+        );
+        if let Some(i) = intersection {
+            if let Some(intersection) =
+                mesh_to_world_intersection(mesh_to_world, &mesh_space_ray, i, max_distance)
+            {
+                pick_intersection = Some(triangles.interpolate(intersection, triangle, vertices));
                 min_pick_distance = i.distance();
             }
         }
@@ -192,26 +92,58 @@ fn _compute_intersection(
     pick_intersection
 }
 
+/// Transforms an intersection computed with a mesh space ray to world space, discarding it if it
+/// is further than `max_distance` in world space.
+pub fn mesh_to_world_intersection(
+    mesh_to_world: &Mat4,
+    mesh_space_ray: &Ray3d,
+    intersection: Intersection,
+    max_distance: f32,
+) -> Option<Intersection> {
+    let distance = mesh_to_world
+        .transform_vector3(mesh_space_ray.direction() * intersection.distance)
+        .length();
+    if distance > max_distance {
+        return None;
+    }
+    Some(
+        Intersection::new(
+            mesh_to_world.transform_point3(intersection.position),
+            mesh_to_world.transform_vector3(intersection.normal),
+            distance,
+            intersection.triangle.map(|tri| {
+                Triangle::from([
+                    mesh_to_world.transform_point3a(tri.v0),
+                    mesh_to_world.transform_point3a(tri.v1),
+                    mesh_to_world.transform_point3a(tri.v2),
+                ])
+            }),
+        )
+        .with_barycentric_coords(intersection.barycentric_coords),
+    )
+}
+
 pub fn triangle_intersection(
     tri_vertices: [Vec3A; 3],
     tri_normals: Option<[Vec3A; 3]>,
     max_distance: f32,
     ray: Ray3d,
+    backfaces: Backfaces,
+    algorithm: IntersectionAlgorithm,
 ) -> Option<Intersection> {
     if tri_vertices
         .iter()
         .any(|&vertex| (vertex - ray.origin).length_squared() < max_distance.powi(2))
     {
         // Run the raycast on the ray and triangle
-        if let Some(ray_hit) = ray_triangle_intersection(&ray, &tri_vertices, Backfaces::default())
+        if let Some(ray_hit) = ray_triangle_intersection(&ray, &tri_vertices, backfaces, algorithm)
         {
             let distance = *ray_hit.distance();
             if distance > 0.0 && distance < max_distance {
                 let position = ray.position(distance);
+                let (u, v) = *ray_hit.uv_coords();
+                let w = 1.0 - u - v;
                 let normal = if let Some(normals) = tri_normals {
-                    let u = ray_hit.uv_coords().0;
-                    let v = ray_hit.uv_coords().1;
-                    let w = 1.0 - u - v;
                     normals[1] * u + normals[2] * v + normals[0] * w
                 } else {
                     (tri_vertices.v1() - tri_vertices.v0())
@@ -223,7 +155,8 @@ pub fn triangle_intersection(
                     normal.into(),
                     distance,
                     Some(tri_vertices.to_triangle()),
-                );
+                )
+                .with_barycentric_coords(Vec3::new(w, u, v));
                 return Some(intersection);
             }
         }
@@ -237,8 +170,14 @@ pub fn ray_triangle_intersection(
     ray: &Ray3d,
     triangle: &impl TriangleTrait,
     backface_culling: Backfaces,
+    algorithm: IntersectionAlgorithm,
 ) -> Option<RayHit> {
-    raycast_moller_trumbore(ray, triangle, backface_culling)
+    match algorithm {
+        IntersectionAlgorithm::MollerTrumbore => {
+            raycast_moller_trumbore(ray, triangle, backface_culling)
+        }
+        IntersectionAlgorithm::Watertight => raycast_watertight(ray, triangle, backface_culling),
+    }
 }
 
 /// Implementation of the Möller-Trumbore ray-triangle intersection test
@@ -292,3 +231,167 @@ pub fn raycast_moller_trumbore(
         uv_coords: (u, v),
     })
 }
+
+/// Implementation of the watertight ray-triangle intersection test
+pub fn raycast_watertight(
+    ray: &Ray3d,
+    triangle: &impl TriangleTrait,
+    backface_culling: Backfaces,
+) -> Option<RayHit> {
+    // Source: Woop, Benthin and Wald, "Watertight Ray/Triangle Intersection", JCGT 2013
+    // Shear and scale the vertices so the ray points along +z from the origin, which reduces the
+    // test to the signs of 2D edge functions that are exactly consistent across shared edges.
+    let direction: [f32; 3] = ray.direction.into();
+    let abs_direction = ray.direction.abs();
+    let kz = if abs_direction.x > abs_direction.y && abs_direction.x > abs_direction.z {
+        0
+    } else if abs_direction.y > abs_direction.z {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    // Swap to preserve the winding direction of the triangle
+    if direction[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    let shear_x = direction[kx] / direction[kz];
+    let shear_y = direction[ky] / direction[kz];
+    let shear_z = 1.0 / direction[kz];
+
+    let a: [f32; 3] = (triangle.v0() - ray.origin).into();
+    let b: [f32; 3] = (triangle.v1() - ray.origin).into();
+    let c: [f32; 3] = (triangle.v2() - ray.origin).into();
+
+    let (ax, ay) = (a[kx] - shear_x * a[kz], a[ky] - shear_y * a[kz]);
+    let (bx, by) = (b[kx] - shear_x * b[kz], b[ky] - shear_y * b[kz]);
+    let (cx, cy) = (c[kx] - shear_x * c[kz], c[ky] - shear_y * c[kz]);
+
+    let mut u = cx * by - cy * bx;
+    let mut v = ax * cy - ay * cx;
+    let mut w = bx * ay - by * ax;
+
+    // Recompute the edge functions in double precision when a ray hits an edge exactly
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+
+    match backface_culling {
+        Backfaces::Cull => {
+            if u < 0.0 || v < 0.0 || w < 0.0 {
+                return None;
+            }
+        }
+        Backfaces::Include => {
+            if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+                return None;
+            }
+        }
+    }
+
+    let determinant = u + v + w;
+    if determinant == 0.0 {
+        return None;
+    }
+
+    let t = u * shear_z * a[kz] + v * shear_z * b[kz] + w * shear_z * c[kz];
+    let determinant_inverse = 1.0 / determinant;
+
+    // The weights of v1 and v2, matching the uv coordinates of Möller-Trumbore
+    Some(RayHit {
+        distance: t * determinant_inverse,
+        uv_coords: (v * determinant_inverse, w * determinant_inverse),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::{mesh::Indices, render_resource::PrimitiveTopology};
+
+    use super::*;
+
+    /// Skewed quad split along the diagonal from the first to the third vertex.
+    fn quad() -> (Mesh, [Vec3; 4]) {
+        let corners = [
+            Vec3::new(0.1, 0.3, 0.0),
+            Vec3::new(1.7, 0.2, 0.0),
+            Vec3::new(1.9, 1.3, 0.0),
+            Vec3::new(0.2, 1.1, 0.0),
+        ];
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            corners
+                .iter()
+                .map(|corner| corner.to_array())
+                .collect::<Vec<_>>(),
+        );
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 2, 0, 2, 3])));
+        (mesh, corners)
+    }
+
+    /// Casts a ray from the offset to the point and returns the intersection with the quad and
+    /// the number of its triangles the ray hits.
+    fn cast_at(point: Vec3, offset: Vec3) -> (Option<Intersection>, usize) {
+        let (mesh, corners) = quad();
+        let origin = point + offset;
+        let ray = Ray3d::new(origin, point - origin);
+        let triangles = [
+            [corners[0], corners[1], corners[2]],
+            [corners[0], corners[2], corners[3]],
+        ];
+        let triangle_hits = triangles
+            .iter()
+            .filter(|triangle| {
+                let triangle = triangle.map(Vec3A::from);
+                raycast_watertight(&ray, &triangle, Backfaces::Include).is_some()
+            })
+            .count();
+        let intersection = compute_intersection(
+            &mesh,
+            &Mat4::IDENTITY,
+            &ray,
+            Backfaces::Include,
+            IntersectionAlgorithm::Watertight,
+            f32::MAX,
+        )
+        .unwrap();
+        (intersection, triangle_hits)
+    }
+
+    #[test]
+    fn watertight_shared_edge() {
+        let (_, corners) = quad();
+        for step in 1..100 {
+            let point = corners[0].lerp(corners[2], step as f32 / 100.0);
+            let (intersection, triangle_hits) = cast_at(point, Vec3::new(0.37, -0.21, 5.0));
+            assert!(
+                triangle_hits >= 1,
+                "ray slipped through the edge at {}",
+                point
+            );
+            let intersection = intersection.unwrap();
+            assert!((intersection.position - point).length() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn watertight_shared_vertex() {
+        let (_, corners) = quad();
+        // The shared vertices are corners of the quad, so a slanted ray could graze past them
+        for corner in [corners[0], corners[2]] {
+            let (intersection, triangle_hits) = cast_at(corner, Vec3::Z * 5.0);
+            assert!(
+                triangle_hits >= 1,
+                "ray slipped through the vertex at {}",
+                corner
+            );
+            let intersection = intersection.unwrap();
+            assert!((intersection.position - corner).length() < 1e-4);
+        }
+    }
+}